@@ -7,6 +7,9 @@ use tag::behavior::chasing::ChasingBehavior;
 use tag::behavior::default::DefaultBehavior;
 use tag::behavior::runaway::RunawayBehavior;
 use tag::behavior::Behavior;
+use tag::execution::ExecutionStrategy;
+use tag::layout::AgentLayout;
+use tag::physics::Physics;
 use tag::types::Vector;
 use tag::world::World;
 
@@ -98,9 +101,52 @@ fn parallel_runaway_behavior_1_000_agents(bench: &mut Bencher) {
 	bench_with_random_world::<RunawayBehavior>(bench, 1_000, true);
 }
 
+fn struct_of_arrays_chasing_behavior_1_000_agents(bench: &mut Bencher) {
+	bench_with_random_world_in_layout::<ChasingBehavior>(bench, 1_000, false, AgentLayout::StructOfArrays);
+}
+
+fn struct_of_arrays_chasing_behavior_10_000_agents(bench: &mut Bencher) {
+	bench_with_random_world_in_layout::<ChasingBehavior>(bench, 10_000, false, AgentLayout::StructOfArrays);
+}
+
+fn struct_of_arrays_runaway_behavior_1_000_agents(bench: &mut Bencher) {
+	bench_with_random_world_in_layout::<RunawayBehavior>(bench, 1_000, false, AgentLayout::StructOfArrays);
+}
+
+fn sharded_default_behavior_100_000_agents(bench: &mut Bencher) {
+	bench_sharded::<DefaultBehavior>(bench, 100_000, 64);
+}
+
+fn sharded_default_behavior_1_000_000_agents(bench: &mut Bencher) {
+	bench_sharded::<DefaultBehavior>(bench, 1_000_000, 64);
+}
+
+fn sharded_chasing_behavior_100_000_agents(bench: &mut Bencher) {
+	bench_sharded::<ChasingBehavior>(bench, 100_000, 64);
+}
+
+fn automatic_default_behavior_10_agents(bench: &mut Bencher) {
+	bench_automatic::<DefaultBehavior>(bench, 10);
+}
+
+fn automatic_default_behavior_100_000_agents(bench: &mut Bencher) {
+	bench_automatic::<DefaultBehavior>(bench, 100_000);
+}
+
 fn bench_with_random_world<BehaviorType>(bench: &mut Bencher, agent_count: usize, parallel: bool)
 where
 	BehaviorType: Behavior + Default + Send + Sync + 'static,
+{
+	bench_with_random_world_in_layout::<BehaviorType>(bench, agent_count, parallel, AgentLayout::default());
+}
+
+fn bench_with_random_world_in_layout<BehaviorType>(
+	bench: &mut Bencher,
+	agent_count: usize,
+	parallel: bool,
+	layout: AgentLayout,
+) where
+	BehaviorType: Behavior + Default + Send + Sync + 'static,
 {
 	let bounds = Vector::new(1000.0, 1000.0);
 	let mut random_generator = SmallRng::from_entropy();
@@ -110,7 +156,8 @@ where
 		BehaviorType::default,
 		parallel,
 		&mut random_generator,
-	);
+	)
+	.with_layout(layout);
 
 	bench.iter(|| world.simulate_step());
 }
@@ -161,6 +208,56 @@ benchmark_group!(
 	parallel_runaway_behavior_1_000_agents,
 );
 
+fn bench_sharded<BehaviorType>(bench: &mut Bencher, agent_count: usize, shard_count: usize)
+where
+	BehaviorType: Behavior + Default + Send + Sync + 'static,
+{
+	let bounds = Vector::new(1000.0, 1000.0);
+	let mut random_generator = SmallRng::from_entropy();
+	// every agent sees about 1250 others with a million agents
+	let physics = Physics {
+		view_distance: 20.0,
+		..Physics::default()
+	};
+	let mut world = World::random(bounds, agent_count, BehaviorType::default, true, &mut random_generator)
+		.with_physics(physics)
+		.with_shards(shard_count);
+
+	bench.iter(|| world.simulate_step());
+}
+
+fn bench_automatic<BehaviorType>(bench: &mut Bencher, agent_count: usize)
+where
+	BehaviorType: Behavior + Default + Send + Sync + 'static,
+{
+	let bounds = Vector::new(1000.0, 1000.0);
+	let mut random_generator = SmallRng::from_entropy();
+	let mut world = World::random(bounds, agent_count, BehaviorType::default, false, &mut random_generator)
+		.with_execution_strategy(ExecutionStrategy::Automatic);
+
+	bench.iter(|| world.simulate_step());
+}
+
+benchmark_group!(
+	struct_of_arrays,
+	struct_of_arrays_chasing_behavior_1_000_agents,
+	struct_of_arrays_chasing_behavior_10_000_agents,
+	struct_of_arrays_runaway_behavior_1_000_agents,
+);
+
+benchmark_group!(
+	sharded,
+	sharded_default_behavior_100_000_agents,
+	sharded_default_behavior_1_000_000_agents,
+	sharded_chasing_behavior_100_000_agents,
+);
+
+benchmark_group!(
+	automatic,
+	automatic_default_behavior_10_agents,
+	automatic_default_behavior_100_000_agents,
+);
+
 benchmark_main!(
 	default_behavior,
 	parallel_default_behavior,
@@ -168,4 +265,7 @@ benchmark_main!(
 	parallel_chasing_behavior,
 	runaway_behavior,
 	parallel_runaway_behavior,
+	struct_of_arrays,
+	sharded,
+	automatic,
 );
@@ -1,4 +1,5 @@
-use crate::types::{normalize_radians, rotate_by_angle, Radians, Vector};
+use crate::physics::Physics;
+use crate::types::{normalize_radians, normalize_signed_radians, rotate_by_angle, Radians, Vector};
 use rand::Rng;
 use std::f32::consts::PI;
 
@@ -20,6 +21,8 @@ impl Agent {
 	pub const MAXIMUM_VELOCITY: f32 = 5.0;
 	/// How far an agent can reach
 	pub const RANGE: f32 = 10.0;
+	/// How far an agent can see, there is no limit
+	pub const VIEW_DISTANCE: f32 = f32::INFINITY;
 
 	pub fn random(bounds: Vector, random_generator: &mut impl Rng) -> Self {
 		let position = Vector::new(
@@ -45,8 +48,14 @@ impl Agent {
 	}
 
 	/// Can the other agent be reached?
+	#[deprecated(note = "Use `Agent::can_reach_with_physics`, the range depends on the physics of the world")]
 	pub fn can_reach(&self, other: &Agent) -> bool {
-		self.distance(other) <= Self::RANGE
+		self.can_reach_with_physics(other, &Physics::default())
+	}
+
+	/// Can the other agent be reached with the given [`Physics`]?
+	pub fn can_reach_with_physics(&self, other: &Agent, physics: &Physics) -> bool {
+		physics.is_reachable(&self.relate_to(other))
 	}
 
 	/// At what angle would this agent see the other one based on its current heading.
@@ -59,20 +68,36 @@ impl Agent {
 		}
 
 		let vector = other.position - self.position;
-		let absolute_angle = Vector::new(1.0, 0.0).angle_between(vector);
-		normalize_radians(absolute_angle - self.heading)
+		normalize_signed_radians(vector.y.atan2(vector.x) - self.heading)
 	}
 
 	/// Does this agent see the other one?
+	#[deprecated(note = "Use `Agent::can_see_with_physics`, the field of view depends on the physics of the world")]
 	pub fn can_see(&self, other: &Agent) -> bool {
-		self.viewing_angle(other).abs() <= (Self::FIELD_OF_VIEW_ANGLE / 2.0)
+		self.can_see_with_physics(other, &Physics::default())
+	}
+
+	/// Does this agent see the other one with the given [`Physics`]?
+	pub fn can_see_with_physics(&self, other: &Agent, physics: &Physics) -> bool {
+		physics.is_visible(&self.relate_to(other))
 	}
 
 	/// Moves with the given velocity in the given direction
 	/// If the agent hits the wall, it stops there.
 	pub fn perform_movement(&self, bounds: Vector, velocity: f32, direction: Radians) -> Self {
+		self.perform_movement_with_physics(&Physics::default(), bounds, velocity, direction)
+	}
+
+	/// Same as [`Agent::perform_movement`], but the velocity is capped by the given [`Physics`]
+	pub fn perform_movement_with_physics(
+		&self,
+		physics: &Physics,
+		bounds: Vector,
+		velocity: f32,
+		direction: Radians,
+	) -> Self {
 		let heading = normalize_radians(direction);
-		let velocity = velocity.min(Self::MAXIMUM_VELOCITY);
+		let velocity = velocity.min(physics.maximum_velocity);
 
 		let movement = rotate_by_angle(Vector::X * velocity, heading);
 
@@ -95,13 +120,15 @@ pub struct AgentRelationShip {
 
 impl AgentRelationShip {
 	/// Can the other agent be reached by us?
+	#[deprecated(note = "Use `Physics::is_reachable`, the range depends on the physics of the world")]
 	pub fn is_reachable(&self) -> bool {
-		self.distance <= Agent::RANGE
+		Physics::default().is_reachable(self)
 	}
 
 	/// Can the other agent be seen by us?
+	#[deprecated(note = "Use `Physics::is_visible`, the field of view depends on the physics of the world")]
 	pub fn is_visible(&self) -> bool {
-		self.direction.abs() <= (Agent::FIELD_OF_VIEW_ANGLE / 2.0)
+		Physics::default().is_visible(self)
 	}
 }
 
@@ -149,49 +176,97 @@ mod test {
 
 	#[test]
 	fn should_check_if_another_agent_is_seen() {
+		let physics = Physics::default();
 		let center = Vector::new(10.0, 10.0);
 
 		let looking_agent = Agent {
 			position: center,
 			heading: degrees_to_radians(45.0),
 		};
-		assert!(looking_agent.can_see(&looking_agent));
+		assert!(looking_agent.can_see_with_physics(&looking_agent, &physics));
 
 		let out_of_view_left = Agent {
 			position: center
 				+ rotate_by_angle(
 					Vector::X,
-					looking_agent.heading + (Agent::FIELD_OF_VIEW_ANGLE / 2.0) + 0.1,
+					looking_agent.heading + (physics.field_of_view_angle / 2.0) + 0.1,
 				),
 			heading: 0.0,
 		};
-		assert!(!looking_agent.can_see(&out_of_view_left));
+		assert!(!looking_agent.can_see_with_physics(&out_of_view_left, &physics));
 
 		let out_of_view_right = Agent {
 			position: center
 				+ rotate_by_angle(
 					Vector::X,
-					looking_agent.heading - (Agent::FIELD_OF_VIEW_ANGLE / 2.0) - 0.1,
+					looking_agent.heading - (physics.field_of_view_angle / 2.0) - 0.1,
 				),
 			heading: 0.0,
 		};
-		assert!(!looking_agent.can_see(&out_of_view_right));
+		assert!(!looking_agent.can_see_with_physics(&out_of_view_right, &physics));
 
 		let in_view = Agent {
 			position: center + rotate_by_angle(Vector::X, looking_agent.heading),
 			heading: 0.0,
 		};
-		assert!(looking_agent.can_see(&in_view));
+		assert!(looking_agent.can_see_with_physics(&in_view, &physics));
 
 		let just_in_view = Agent {
 			position: center
 				+ rotate_by_angle(
 					Vector::X,
-					looking_agent.heading + (Agent::FIELD_OF_VIEW_ANGLE / 2.0) - 0.1,
+					looking_agent.heading + (physics.field_of_view_angle / 2.0) - 0.1,
 				),
 			heading: 0.0,
 		};
-		assert!(looking_agent.can_see(&just_in_view));
+		assert!(looking_agent.can_see_with_physics(&just_in_view, &physics));
+
+		let just_in_view_right = Agent {
+			position: center
+				+ rotate_by_angle(
+					Vector::X,
+					looking_agent.heading - (physics.field_of_view_angle / 2.0) + 0.1,
+				),
+			heading: 0.0,
+		};
+		assert!(looking_agent.can_see_with_physics(&just_in_view_right, &physics));
+	}
+
+	#[test]
+	fn should_return_viewing_angles_to_the_right_as_negative() {
+		let looking_agent = Agent {
+			position: Vector::new(10.0, 10.0),
+			heading: degrees_to_radians(45.0),
+		};
+
+		let seen_agent = Agent {
+			position: Vector::new(11.0, 9.0),
+			heading: 0.0,
+		};
+
+		assert_eq!(
+			-90.0,
+			radians_to_degrees(looking_agent.viewing_angle(&seen_agent)).round()
+		);
+	}
+
+	#[test]
+	fn should_check_if_another_agent_is_reached() {
+		let physics = Physics {
+			range: 2.0,
+			..Default::default()
+		};
+		let agent = Agent {
+			position: Vector::ZERO,
+			heading: 0.0,
+		};
+		let other = Agent {
+			position: Vector::new(0.0, 2.0),
+			heading: 0.0,
+		};
+
+		assert!(agent.can_reach_with_physics(&other, &physics));
+		assert!(!agent.can_reach_with_physics(&other, &Physics { range: 1.5, ..physics }));
 	}
 
 	#[test]
@@ -1,12 +1,13 @@
 use static_assertions::assert_obj_safe;
 
-use crate::agent::Agent;
 use crate::id::Id;
 use crate::types::Radians;
 use crate::world::WorldView;
 
 pub mod chasing;
 pub mod default;
+pub mod human;
+pub mod registry;
 pub mod runaway;
 
 /// This trait needs to be implemented to give an Agent a Behavior.
@@ -17,8 +18,21 @@ pub mod runaway;
 /// is exactly one instance of a [`Behavior`] per agent.
 pub trait Behavior {
 	fn perform_step(&mut self, world_view: &mut WorldView) -> Operation;
+
+	/// Name of the behavior, used to attribute scores to behavior types
+	fn name(&self) -> &'static str {
+		std::any::type_name::<Self>()
+	}
+
+	/// The agent this behavior went after in the last step, if any. Only used for visualization and debugging.
+	fn target(&self) -> Option<Id> {
+		None
+	}
 }
 
+/// A [`Behavior`] as it is stored by the simulation engine
+pub type BoxedBehavior = Box<dyn Behavior + Send + Sync + 'static>;
+
 /// Operation to be performed by an agent in a simulation step
 pub struct Operation {
 	/// Direction to move in.
@@ -35,16 +49,16 @@ pub struct Operation {
 assert_obj_safe!(Behavior);
 
 pub(crate) fn catch_reachable(world_view: &mut WorldView, runaway_direction: Radians) -> Option<Operation> {
+	let maximum_velocity = world_view.physics().maximum_velocity;
 	let previous_it = world_view.previous_it();
 	world_view
-		.reachable_agents()
-		.iter()
-		.find(|(&id, _)| id != previous_it)
-		.map(|(&taggable_id, _)| {
+		.reachable()
+		.find(|(id, _)| *id != previous_it)
+		.map(|(taggable_id, _)| {
 			// Tag the first reachable agent and run away
 			Operation {
 				direction: runaway_direction,
-				velocity: Agent::MAXIMUM_VELOCITY,
+				velocity: maximum_velocity,
 				tag: Some(taggable_id),
 			}
 		})
@@ -52,17 +66,15 @@ pub(crate) fn catch_reachable(world_view: &mut WorldView, runaway_direction: Rad
 
 pub(crate) fn chase_nearest(world_view: &mut WorldView) -> Option<(Operation, Id)> {
 	let heading = world_view.our_agent().heading;
+	let maximum_velocity = world_view.physics().maximum_velocity;
 	let previous_it = world_view.previous_it();
 	world_view
-		.visible_agents()
-		.iter()
-		.filter(|(&id, _)| id != previous_it)
-		.min_by(|(_, a), (_, b)| a.distance.partial_cmp(&b.distance).expect("Invalid distance"))
-		.map(|(&nearest_id, nearest)| {
+		.nearest_visible(|id, _| id != previous_it)
+		.map(|(nearest_id, nearest)| {
 			(
 				Operation {
 					direction: heading + nearest.direction,
-					velocity: Agent::MAXIMUM_VELOCITY,
+					velocity: maximum_velocity,
 					tag: None,
 				},
 				nearest_id,
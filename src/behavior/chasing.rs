@@ -1,4 +1,3 @@
-use crate::agent::Agent;
 use crate::behavior::default::DefaultBehavior;
 use crate::behavior::{catch_reachable, chase_nearest, Behavior, Operation};
 use crate::id::Id;
@@ -11,10 +10,29 @@ use rand::{thread_rng, Rng};
 #[derive(Default)]
 pub struct ChasingBehavior {
 	chasing: Option<Id>,
+	/// Who was chased or tagged in the last step
+	target: Option<Id>,
 }
 
 impl Behavior for ChasingBehavior {
 	fn perform_step(&mut self, world_view: &mut WorldView) -> Operation {
+		let (operation, target) = self.choose_operation(world_view);
+		self.target = target;
+		operation
+	}
+
+	fn name(&self) -> &'static str {
+		"chasing"
+	}
+
+	fn target(&self) -> Option<Id> {
+		self.target
+	}
+}
+
+impl ChasingBehavior {
+	/// Decide what to do and who to go after
+	fn choose_operation(&mut self, world_view: &mut WorldView) -> (Operation, Option<Id>) {
 		// more likely to go right
 		let random_angle = degrees_to_radians(10.0 * (thread_rng().gen_range(-1i8..=2) as f32));
 
@@ -22,45 +40,44 @@ impl Behavior for ChasingBehavior {
 		let our_id = world_view.our_id();
 		let our_agent = world_view.our_agent().clone();
 		if it != our_id {
-			return DefaultBehavior.perform_step(world_view);
+			return (DefaultBehavior.perform_step(world_view), None);
 		}
 
 		// we're "it", is somebody near enough to tag?
 		if let Some(operation) = catch_reachable(world_view, our_agent.heading + random_angle) {
-			return operation;
+			let tagged = operation.tag;
+			return (operation, tagged);
 		}
 
 		// Are we chasing someone and is that person visible? If so, keep chasing!
 		if let Some(chased_id) = self.chasing {
 			if let Some(operation) = chase_id(world_view, chased_id) {
-				return operation;
+				return (operation, Some(chased_id));
 			}
 		}
 
 		// Nobody is reachable or being chased, see who's nearest
 		if let Some((operation, nearest_id)) = chase_nearest(world_view) {
 			self.chasing = Some(nearest_id);
-			return operation;
+			return (operation, Some(nearest_id));
 		}
 
 		// Can't see anybody, turn around, maybe we see someone
-		Operation {
+		let operation = Operation {
 			direction: our_agent.heading + random_angle,
 			velocity: 0.0,
 			tag: None,
-		}
+		};
+		(operation, None)
 	}
 }
 
 fn chase_id(world_view: &mut WorldView, chased: Id) -> Option<Operation> {
 	let heading = world_view.our_agent().heading;
-	world_view
-		.visible_agents()
-		.iter()
-		.find(|(&id, _)| id == chased)
-		.map(|(_, chased)| Operation {
-			direction: heading + chased.direction,
-			velocity: Agent::MAXIMUM_VELOCITY,
-			tag: None,
-		})
+	let maximum_velocity = world_view.physics().maximum_velocity;
+	world_view.relation_to(chased).map(|chased| Operation {
+		direction: heading + chased.direction,
+		velocity: maximum_velocity,
+		tag: None,
+	})
 }
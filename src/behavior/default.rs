@@ -1,6 +1,5 @@
 use rand::{thread_rng, Rng};
 
-use crate::agent::Agent;
 use crate::behavior::{catch_reachable, chase_nearest, Behavior, Operation};
 use crate::types::degrees_to_radians;
 use crate::world::WorldView;
@@ -17,6 +16,7 @@ pub struct DefaultBehavior;
 impl Behavior for DefaultBehavior {
 	fn perform_step(&mut self, world_view: &mut WorldView) -> Operation {
 		let our_agent = world_view.our_agent().clone();
+		let maximum_velocity = world_view.physics().maximum_velocity;
 
 		// more likely to go right
 		let random_angle = degrees_to_radians(10.0 * (thread_rng().gen_range(-1i8..=2) as f32));
@@ -25,7 +25,7 @@ impl Behavior for DefaultBehavior {
 			// we're not "it", run in a random direction with full speed
 			return Operation {
 				direction: our_agent.heading + random_angle,
-				velocity: Agent::MAXIMUM_VELOCITY,
+				velocity: maximum_velocity,
 				tag: None,
 			};
 		}
@@ -47,4 +47,8 @@ impl Behavior for DefaultBehavior {
 			tag: None,
 		}
 	}
+
+	fn name(&self) -> &'static str {
+		"default"
+	}
 }
@@ -0,0 +1,145 @@
+use crate::behavior::{Behavior, Operation};
+use crate::id::Id;
+use crate::types::Radians;
+use crate::world::WorldView;
+use std::sync::{Arc, Mutex};
+
+/// Handle to steer [`HumanBehavior`]s from other threads, e.g. from the keyboard input of a viewer.
+#[derive(Clone, Default)]
+pub struct HumanInput {
+	shared: Arc<Mutex<InputState>>,
+}
+
+#[derive(Default)]
+struct InputState {
+	/// Direction to move in, standing still if `None`
+	direction: Option<Radians>,
+	/// Fraction of the maximum velocity
+	speed: f32,
+	tag_requested: bool,
+	agent: Option<Id>,
+}
+
+/// What to do in the next step
+struct Command {
+	direction: Option<Radians>,
+	speed: f32,
+	tag: bool,
+}
+
+impl HumanInput {
+	/// Move in the given direction with the given fraction (between 0 and 1) of the maximum velocity,
+	/// `None` stands still
+	pub fn set_movement(&self, direction: Option<Radians>, speed: f32) {
+		let mut state = self.shared.lock().expect("Lock was poisoned");
+		state.direction = direction;
+		state.speed = speed.clamp(0.0, 1.0);
+	}
+
+	/// Try to tag the nearest reachable agent in the next step
+	pub fn request_tag(&self) {
+		self.shared.lock().expect("Lock was poisoned").tag_requested = true;
+	}
+
+	/// The agent that is controlled by this input, known once the simulation ran a step
+	pub fn agent(&self) -> Option<Id> {
+		self.shared.lock().expect("Lock was poisoned").agent
+	}
+
+	/// Take the command for the next step of the given agent, a tag is only attempted once
+	fn take_command(&self, agent: Id) -> Command {
+		let mut state = self.shared.lock().expect("Lock was poisoned");
+		state.agent = Some(agent);
+		Command {
+			direction: state.direction,
+			speed: state.speed,
+			tag: std::mem::take(&mut state.tag_requested),
+		}
+	}
+}
+
+/// Behavior that is controlled by a person through a [`HumanInput`], e.g. with the keyboard in the visual viewer
+pub struct HumanBehavior {
+	input: HumanInput,
+	/// Who was tagged in the last step
+	target: Option<Id>,
+}
+
+impl HumanBehavior {
+	pub fn new(input: HumanInput) -> Self {
+		Self { input, target: None }
+	}
+}
+
+impl Behavior for HumanBehavior {
+	fn perform_step(&mut self, world_view: &mut WorldView) -> Operation {
+		let command = self.input.take_command(world_view.our_id());
+		let heading = world_view.our_agent().heading;
+		let maximum_velocity = world_view.physics().maximum_velocity;
+
+		self.target = None;
+		if command.tag && (world_view.our_id() == world_view.current_it()) {
+			let previous_it = world_view.previous_it();
+			self.target = world_view
+				.reachable()
+				.filter(|(id, _)| *id != previous_it)
+				.min_by(|(_, a), (_, b)| a.distance.total_cmp(&b.distance))
+				.map(|(id, _)| id);
+		}
+
+		Operation {
+			direction: command.direction.unwrap_or(heading),
+			velocity: if command.direction.is_some() {
+				command.speed * maximum_velocity
+			} else {
+				0.0
+			},
+			tag: self.target,
+		}
+	}
+
+	fn name(&self) -> &'static str {
+		"human"
+	}
+
+	fn target(&self) -> Option<Id> {
+		self.target
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::behavior::default::DefaultBehavior;
+	use crate::behavior::BoxedBehavior;
+	use crate::types::Vector;
+	use crate::world::World;
+	use rand::rngs::SmallRng;
+	use rand::SeedableRng;
+	use std::f32::consts::FRAC_PI_2;
+
+	#[test]
+	fn should_move_as_instructed() {
+		let input = HumanInput::default();
+		let behaviors: Vec<BoxedBehavior> =
+			vec![Box::new(HumanBehavior::new(input.clone())), Box::new(DefaultBehavior)];
+		let mut world = World::random_with_behaviors(
+			Vector::new(1000.0, 1000.0),
+			behaviors,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		);
+		let start = world.agents()[0].position;
+
+		input.set_movement(None, 1.0);
+		world.simulate_step();
+		assert_eq!(start, world.agents()[0].position);
+		assert_eq!(Some(Id::from(0)), input.agent());
+
+		input.set_movement(Some(FRAC_PI_2), 0.5);
+		world.simulate_step();
+		assert!((world.agents()[0].heading - FRAC_PI_2).abs() < 1e-6);
+		let distance = world.agents()[0].position.distance(start);
+		assert!(distance <= 0.5 * world.physics().maximum_velocity + 1e-4);
+	}
+}
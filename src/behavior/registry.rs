@@ -0,0 +1,60 @@
+use crate::behavior::chasing::ChasingBehavior;
+use crate::behavior::default::DefaultBehavior;
+use crate::behavior::runaway::RunawayBehavior;
+use crate::behavior::{Behavior, BoxedBehavior};
+
+type BehaviorConstructor = Box<dyn Fn() -> BoxedBehavior + Send + Sync>;
+
+/// Maps behavior names to constructors, so behaviors can be chosen by name (e.g. from the command line).
+///
+/// The [`Default`] registry contains all behaviors that ship with this crate,
+/// additional behaviors can be added with [`BehaviorRegistry::register`].
+pub struct BehaviorRegistry {
+	constructors: Vec<(&'static str, BehaviorConstructor)>,
+}
+
+impl Default for BehaviorRegistry {
+	fn default() -> Self {
+		let mut registry = Self::empty();
+		registry.register("default", DefaultBehavior::default);
+		registry.register("chasing", ChasingBehavior::default);
+		registry.register("runaway", RunawayBehavior::default);
+		registry
+	}
+}
+
+impl BehaviorRegistry {
+	/// A registry without any behaviors
+	pub fn empty() -> Self {
+		Self {
+			constructors: Vec::new(),
+		}
+	}
+
+	/// Register a behavior under the given name. An existing behavior with the same name is replaced.
+	pub fn register<BehaviorType>(
+		&mut self,
+		name: &'static str,
+		constructor: impl Fn() -> BehaviorType + Send + Sync + 'static,
+	) where
+		BehaviorType: Behavior + Send + Sync + 'static,
+	{
+		self.constructors.retain(|(existing_name, _)| *existing_name != name);
+		self.constructors
+			.push((name, Box::new(move || Box::new(constructor()) as BoxedBehavior)));
+	}
+
+	/// Construct a new instance of the behavior with the given name
+	pub fn create(&self, name: &str) -> Result<BoxedBehavior, String> {
+		self.constructors
+			.iter()
+			.find(|(registered_name, _)| *registered_name == name)
+			.map(|(_, constructor)| constructor())
+			.ok_or_else(|| format!("Unknown behavior: {name} (available: {})", self.names().join(", ")))
+	}
+
+	/// Names of all registered behaviors in the order they were registered
+	pub fn names(&self) -> Vec<&'static str> {
+		self.constructors.iter().map(|(name, _)| *name).collect()
+	}
+}
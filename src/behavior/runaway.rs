@@ -1,4 +1,3 @@
-use crate::agent::Agent;
 use crate::behavior::{catch_reachable, chase_nearest, Behavior, Operation};
 use crate::types::{degrees_to_radians, Radians};
 use crate::world::WorldView;
@@ -37,6 +36,7 @@ impl RunawayDirection {
 impl Behavior for RunawayBehavior {
 	fn perform_step(&mut self, world_view: &mut WorldView) -> Operation {
 		let our_agent = world_view.our_agent().clone();
+		let maximum_velocity = world_view.physics().maximum_velocity;
 
 		// more likely to go right
 		let random_angle = degrees_to_radians(10.0 * (thread_rng().gen_range(-1i8..=2) as f32));
@@ -50,7 +50,7 @@ impl Behavior for RunawayBehavior {
 			// we're not "it", run in a random direction with full speed
 			return Operation {
 				direction: our_agent.heading + random_angle,
-				velocity: Agent::MAXIMUM_VELOCITY,
+				velocity: maximum_velocity,
 				tag: None,
 			};
 		}
@@ -72,6 +72,10 @@ impl Behavior for RunawayBehavior {
 			tag: None,
 		}
 	}
+
+	fn name(&self) -> &'static str {
+		"runaway"
+	}
 }
 
 impl RunawayBehavior {
@@ -85,10 +89,11 @@ impl RunawayBehavior {
 			.get_or_insert_with(RunawayDirection::random)
 			.angle();
 		let it = world_view.current_it();
+		let maximum_velocity = world_view.physics().maximum_velocity;
 		// is "it" visible?
-		world_view.visible_agents().get(&it).map(|it_relationship| Operation {
+		world_view.relation_to(it).map(|it_relationship| Operation {
 			direction: it_relationship.direction + runaway_angle,
-			velocity: Agent::MAXIMUM_VELOCITY,
+			velocity: maximum_velocity,
 			tag: None,
 		})
 	}
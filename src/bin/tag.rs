@@ -1,22 +1,244 @@
-use rand::rngs::SmallRng;
-use rand::SeedableRng;
-use std::str::FromStr;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use structopt::StructOpt;
-use tag::behavior::chasing::ChasingBehavior;
-use tag::behavior::default::DefaultBehavior;
-use tag::behavior::runaway::RunawayBehavior;
+use tag::behavior::human::{HumanBehavior, HumanInput};
+use tag::behavior::registry::BehaviorRegistry;
+use tag::execution::ExecutionStrategy;
+use tag::image_export::{FrameRenderer, ImageFormat, ImageViewer};
+use tag::json_lines::JsonLinesViewer;
+use tag::layout::AgentLayout;
+use tag::match_runner::{RoundStart, Scoreboard};
+use tag::physics::Physics;
+use tag::scenario::{AgentGroup, OutputConfiguration, Scenario, ViewerConfiguration, ViewerKind};
+use tag::svg::{SvgRenderer, SvgViewer};
+use tag::sweep::{BoundsValues, OutputFormat, Sweep, Values};
+use tag::terminal::TerminalViewer;
+use tag::tournament::Tournament;
 use tag::types::Vector;
-use tag::viewer::{CommandlineViewer, Viewer};
+use tag::viewer::{CommandlineViewer, CompositeViewer, Viewer};
 use tag::visualization::BevyViewer;
-use tag::world::World;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "tag simulation", about = "Simulating a game of tag.")]
 struct Options {
-	/// How many iterations to simulate
-	#[structopt(default_value = "10000")]
+	/// Scenario file (TOML) to load the configuration from, the other options override it
+	#[structopt(long)]
+	scenario: Option<PathBuf>,
+	/// How many iterations to simulate [default: 10000]
+	iterations: Option<usize>,
+	/// Width of the playing field [default: 500]
+	#[structopt(long)]
+	width: Option<u16>,
+	/// Height of the playing field [default: 500]
+	#[structopt(long)]
+	height: Option<u16>,
+	/// Number of players, the groups of a scenario are scaled to it [default: 10]
+	#[structopt(long)]
+	agent_count: Option<usize>,
+	/// Behavior to use for all agents (default, chasing, runaway or human) [default: default]
+	#[structopt(long)]
+	behavior: Option<String>,
+	/// Seed for placing the agents, random if not given
+	#[structopt(long)]
+	seed: Option<u64>,
+	/// How many iterations to simulate per second, 0 means as fast as possible [default: 20]
+	#[structopt(long)]
+	ticks_per_second: Option<f64>,
+	/// How should the simulation be displayed (visual, command-line, terminal, json-lines, images or svg), multiple viewers can be combined with commas
+	/// [default: visual]
+	#[structopt(long, use_delimiter = true)]
+	viewer: Vec<ViewerKind>,
+	/// File the json-lines viewer writes to [default: stdout]
+	#[structopt(long)]
+	json_lines: Option<PathBuf>,
+	/// Only record every n-th iteration with the json-lines viewer [default: 1]
+	#[structopt(long)]
+	json_lines_interval: Option<usize>,
+	/// File (or directory for png) the images viewer writes to
+	#[structopt(long)]
+	images: Option<PathBuf>,
+	/// Format of the images viewer (png, gif or apng) [default: gif]
+	#[structopt(long)]
+	image_format: Option<ImageFormat>,
+	/// Only render every n-th iteration with the images viewer [default: 1]
+	#[structopt(long)]
+	image_interval: Option<usize>,
+	/// Pixels per unit of the playing field for the images viewer [default: 1]
+	#[structopt(long)]
+	image_scale: Option<f32>,
+	/// File the svg viewer writes the final iteration to
+	#[structopt(long)]
+	svg: Option<PathBuf>,
+	/// Number of iterations the svg viewer draws trails and tags for [default: 50]
+	#[structopt(long)]
+	svg_window: Option<usize>,
+	/// Run the simulation in parallel using rayon
+	#[structopt(long)]
+	parallel: bool,
+	/// Run the simulation on the current thread, even if the scenario runs it in parallel
+	#[structopt(long, conflicts_with = "parallel")]
+	sequential: bool,
+	/// How the behaviors are run (sequential, parallel, chunked:<agents per task> or automatic), automatic measures
+	/// which one is the fastest while the simulation runs [default: sequential, or parallel with --parallel]
+	#[structopt(long)]
+	execution: Option<ExecutionStrategy>,
+	/// How the agents are stored (array-of-structs or struct-of-arrays), struct-of-arrays calculates the relations to
+	/// all agents at once for behaviors that look at many agents [default: array-of-structs]
+	#[structopt(long)]
+	layout: Option<AgentLayout>,
+	/// Simulate the world in about this many regions of the arena in parallel, needs a --view-distance
+	#[structopt(long)]
+	shards: Option<usize>,
+	/// How far the agents can see [default: unlimited]
+	#[structopt(long)]
+	view_distance: Option<f32>,
+	/// Stop the simulation after this many seconds of wall-clock time
+	#[structopt(long)]
+	time_limit_seconds: Option<f64>,
+	/// The game is decided after this many tags
+	#[structopt(long)]
+	tag_limit: Option<usize>,
+	/// Stop the simulation if "it" hasn't seen anybody for this many iterations
+	#[structopt(long)]
+	stalemate_iterations: Option<usize>,
+	/// Number of rounds to play [default: 1]
+	#[structopt(long)]
+	rounds: Option<usize>,
+	/// How the agents are placed at the start of every round after the first one (randomize or reset), reset starts
+	/// every round like the first one [default: randomize]
+	#[structopt(long)]
+	round_start: Option<RoundStart>,
+	#[structopt(subcommand)]
+	command: Option<Command>,
+}
+
+impl Options {
+	/// Load the scenario file (if any) and override it with the options given on the command line
+	fn scenario(&self) -> Result<Scenario, String> {
+		let mut scenario = match &self.scenario {
+			Some(path) => Scenario::load(path)?,
+			None => Scenario::default(),
+		};
+
+		let world = &mut scenario.world;
+		if let Some(width) = self.width {
+			world.width = width as f32;
+		}
+		if let Some(height) = self.height {
+			world.height = height as f32;
+		}
+		if let Some(seed) = self.seed {
+			world.seed = Some(seed);
+		}
+		if self.parallel || self.sequential {
+			world.parallel = self.parallel;
+			world.execution = None;
+		}
+		if let Some(execution) = self.execution {
+			world.execution = Some(execution);
+		}
+		if let Some(layout) = self.layout {
+			world.layout = layout;
+		}
+		if let Some(shards) = self.shards {
+			world.shards = Some(shards);
+		}
+
+		if let Some(behavior) = &self.behavior {
+			scenario.agents = vec![AgentGroup {
+				behavior: behavior.clone(),
+				count: scenario.agent_groups().iter().map(|group| group.count).sum(),
+			}];
+		}
+		if let Some(agent_count) = self.agent_count {
+			scenario.scale_agent_groups(agent_count);
+		}
+
+		let rules = &mut scenario.rules;
+		if let Some(iterations) = self.iterations {
+			rules.iterations = iterations;
+		}
+		if let Some(seconds) = self.time_limit_seconds {
+			rules.time_limit_seconds = Some(seconds);
+		}
+		if let Some(tag_limit) = self.tag_limit {
+			rules.tag_limit = Some(tag_limit);
+		}
+		if let Some(iterations) = self.stalemate_iterations {
+			rules.stalemate_iterations = Some(iterations);
+		}
+		if let Some(rounds) = self.rounds {
+			rules.rounds = rounds;
+		}
+		if let Some(round_start) = self.round_start {
+			rules.round_start = round_start;
+		}
+		if let Some(view_distance) = self.view_distance {
+			rules.physics.view_distance = view_distance;
+		}
+
+		let viewer = &mut scenario.viewer;
+		if !self.viewer.is_empty() {
+			viewer.kinds = self.viewer.clone();
+		}
+		if let Some(ticks_per_second) = self.ticks_per_second {
+			viewer.ticks_per_second = ticks_per_second;
+		}
+
+		let output = &mut scenario.output;
+		if let Some(path) = &self.json_lines {
+			output.json_lines = Some(path.clone());
+		}
+		if let Some(interval) = self.json_lines_interval {
+			output.json_lines_interval = interval;
+		}
+		if let Some(path) = &self.images {
+			output.images = Some(path.clone());
+		}
+		if let Some(format) = self.image_format {
+			output.image_format = format;
+		}
+		if let Some(interval) = self.image_interval {
+			output.image_interval = interval;
+		}
+		if let Some(scale) = self.image_scale {
+			output.image_scale = scale;
+		}
+		if let Some(path) = &self.svg {
+			output.svg = Some(path.clone());
+		}
+		if let Some(window) = self.svg_window {
+			output.svg_window = window;
+		}
+
+		Ok(scenario)
+	}
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+	/// Rate behaviors against each other in a round-robin tournament
+	Tournament(TournamentOptions),
+	/// Run every combination of the given parameters without a viewer and write the results to a file
+	Sweep(SweepOptions),
+}
+
+#[derive(Debug, StructOpt)]
+struct TournamentOptions {
+	/// Behaviors taking part in the tournament (default, chasing or runaway)
+	#[structopt(required = true, min_values = 2)]
+	behaviors: Vec<String>,
+	/// Number of seeds, every pair of behaviors plays one match per seed
+	#[structopt(long, default_value = "20")]
+	seeds: u64,
+	/// Number of agents every behavior controls in a match
+	#[structopt(long, default_value = "5")]
+	agents_per_behavior: usize,
+	/// Maximum number of iterations of a match
+	#[structopt(long, default_value = "2000")]
 	iterations: usize,
 	/// Width of the playing field
 	#[structopt(long, default_value = "500")]
@@ -24,114 +246,171 @@ struct Options {
 	/// Height of the playing field
 	#[structopt(long, default_value = "500")]
 	height: u16,
-	/// Number of players
-	#[structopt(long, default_value = "10")]
-	agent_count: usize,
-	/// Behavior to use for the agents (default, chasing or runaway)
-	#[structopt(long, default_value = "default")]
-	behavior: BehaviorOption,
-	/// Milliseconds to wait between every iteration
-	#[structopt(long, default_value = "50")]
-	delay_milliseconds: u64,
-	/// How should the simulation be displayed (visual or command-line)
-	#[structopt(long, default_value = "visual")]
-	viewer: ViewerOption,
-	/// Run the simulation in parallel using rayon
+	/// A match is decided after this many tags
 	#[structopt(long)]
-	parallel: bool,
+	tag_limit: Option<usize>,
 }
 
 #[derive(Debug, StructOpt)]
-enum ViewerOption {
-	Visual,
-	CommandLine,
+struct SweepOptions {
+	/// Numbers of players, e.g. `10,100..=1000:100`
+	#[structopt(long, default_value = "10")]
+	agent_counts: Values<usize>,
+	/// Sizes of the playing field, e.g. `500x500,1000x1000`
+	#[structopt(long, default_value = "500x500")]
+	bounds: BoundsValues,
+	/// Behaviors to use for the agents, e.g. `default,chasing`
+	#[structopt(long, default_value = "default", use_delimiter = true)]
+	behaviors: Vec<String>,
+	/// How far an agent is allowed to move in one iteration
+	#[structopt(long, default_value = "5")]
+	maximum_velocities: Values<f32>,
+	/// How far an agent can reach
+	#[structopt(long, default_value = "10")]
+	ranges: Values<f32>,
+	/// How wide an agent can see in degrees
+	#[structopt(long, default_value = "200")]
+	field_of_view_degrees: Values<f32>,
+	/// Number of seeds, every combination of parameters is run once per seed
+	#[structopt(long, default_value = "10")]
+	seeds: u64,
+	/// Maximum number of iterations of a run
+	#[structopt(long, default_value = "1000")]
+	iterations: usize,
+	/// A run is decided after this many tags
+	#[structopt(long)]
+	tag_limit: Option<usize>,
+	/// Format of the results (csv or json)
+	#[structopt(long, default_value = "csv")]
+	format: OutputFormat,
+	/// File to write the results to, if not given they are written to stdout
+	#[structopt(long)]
+	output: Option<PathBuf>,
 }
 
-impl FromStr for ViewerOption {
-	type Err = String;
+fn main() {
+	let options = Options::from_args();
+	let mut registry = BehaviorRegistry::default();
 
-	fn from_str(text: &str) -> Result<Self, Self::Err> {
-		use ViewerOption::*;
-		match text {
-			"visual" => Ok(Visual),
-			"command-line" => Ok(CommandLine),
-			_ => Err(format!("Invalid viewer option: {text}")),
+	match options.command {
+		Some(Command::Tournament(tournament_options)) => run_tournament(tournament_options, &registry),
+		Some(Command::Sweep(sweep_options)) => run_sweep(sweep_options, &registry),
+		None => {
+			// all human agents are steered by the same input from the visual viewer
+			let human_input = HumanInput::default();
+			let input = human_input.clone();
+			registry.register("human", move || HumanBehavior::new(input.clone()));
+			run_simulation(options, &registry, human_input)
 		}
 	}
 }
 
-#[derive(Debug, StructOpt)]
-enum BehaviorOption {
-	Default,
-	Chasing,
-	Runaway,
-}
-
-impl FromStr for BehaviorOption {
-	type Err = String;
+fn run_tournament(options: TournamentOptions, registry: &BehaviorRegistry) {
+	let tournament = Tournament {
+		behaviors: options.behaviors,
+		seeds: (0..options.seeds).collect(),
+		agents_per_behavior: options.agents_per_behavior,
+		bounds: Vector::new(options.width as f32, options.height as f32),
+		iterations: options.iterations,
+		tag_limit: options.tag_limit,
+	};
 
-	fn from_str(text: &str) -> Result<Self, Self::Err> {
-		use BehaviorOption::*;
-		match text {
-			"default" => Ok(Default),
-			"chasing" => Ok(Chasing),
-			"runaway" => Ok(Runaway),
-			_ => Err(format!("Invalid behavior option: {text}")),
-		}
+	match tournament.run(registry) {
+		Ok(result) => println!("{result}"),
+		Err(error) => exit_with_error(&error),
 	}
 }
 
-fn main() {
-	let options = Options::from_args();
-
-	let bounds = Vector::new(options.width as f32, options.height as f32);
-	let mut rng = SmallRng::from_entropy();
-
-	let mut world = match options.behavior {
-		BehaviorOption::Default => World::random(
-			bounds,
-			options.agent_count,
-			DefaultBehavior::default,
-			options.parallel,
-			&mut rng,
-		),
-		BehaviorOption::Chasing => World::random(
-			bounds,
-			options.agent_count,
-			ChasingBehavior::default,
-			options.parallel,
-			&mut rng,
-		),
-		BehaviorOption::Runaway => World::random(
-			bounds,
-			options.agent_count,
-			RunawayBehavior::default,
-			options.parallel,
-			&mut rng,
-		),
+fn run_sweep(options: SweepOptions, registry: &BehaviorRegistry) {
+	let sweep = Sweep {
+		agent_counts: options.agent_counts.0,
+		bounds: options.bounds.0,
+		behaviors: options.behaviors,
+		maximum_velocities: options.maximum_velocities.0,
+		ranges: options.ranges.0,
+		field_of_view_degrees: options.field_of_view_degrees.0,
+		seeds: (0..options.seeds).collect(),
+		iterations: options.iterations,
+		tag_limit: options.tag_limit,
 	};
 
-	let viewer = match options.viewer {
-		ViewerOption::Visual => Arc::new(BevyViewer::new(bounds)) as Arc<dyn Viewer>,
-		ViewerOption::CommandLine => Arc::new(CommandlineViewer::default()) as Arc<dyn Viewer>,
+	let rows = sweep.run(registry).unwrap_or_else(|error| exit_with_error(&error));
+	let result = match options.output {
+		Some(path) => File::create(&path)
+			.map_err(|error| format!("Failed to create {}: {error}", path.display()))
+			.and_then(|file| options.format.write(&rows, BufWriter::new(file))),
+		None => options.format.write(&rows, std::io::stdout().lock()),
 	};
+	if let Err(error) = result {
+		exit_with_error(&error);
+	}
+}
 
-	let simulation_handle = std::thread::spawn({
-		let iterations = options.iterations;
-		let iteration_delay = Duration::from_millis(options.delay_milliseconds);
-		let viewer = viewer.clone();
-		move || {
-			for _ in 0..iterations {
-				if !iteration_delay.is_zero() {
-					// in case of zero `sleep` might still sleep the thread, so don't call it in that case
-					std::thread::sleep(iteration_delay);
-				}
+fn run_simulation(options: Options, registry: &BehaviorRegistry, human_input: HumanInput) {
+	let scenario = options.scenario().unwrap_or_else(|error| exit_with_error(&error));
+	let has_human = scenario.agent_groups().iter().any(|group| group.behavior == "human");
+	if has_human && !scenario.viewer.kinds.contains(&ViewerKind::Visual) {
+		exit_with_error("The human behavior can only be steered with the visual viewer");
+	}
+	let simulation = scenario.build(registry).unwrap_or_else(|error| exit_with_error(&error));
 
-				world.simulate_step();
-				viewer.iteration(&world);
+	let bounds = simulation.world.bounds();
+	let physics = *simulation.world.physics();
+	let viewer_configuration = simulation.viewer.clone();
+	let kinds = viewer_configuration.kinds.clone();
+	let output = simulation.output.clone();
+	let results_path = output.results.clone();
+	let runner = simulation.into_runner();
+	let control = runner.control();
+
+	let mut viewers = kinds
+		.iter()
+		.map(|kind| match kind {
+			ViewerKind::Visual => {
+				let mut viewer = BevyViewer::new(bounds)
+					.with_physics(physics)
+					.with_control(control.clone());
+				if has_human {
+					viewer = viewer.with_human_input(human_input.clone());
+				}
+				Arc::new(viewer) as Arc<dyn Viewer>
+			}
+			ViewerKind::CommandLine => Arc::new(CommandlineViewer::default()) as Arc<dyn Viewer>,
+			ViewerKind::Terminal => Arc::new(TerminalViewer::new(control.clone())) as Arc<dyn Viewer>,
+			ViewerKind::JsonLines => Arc::new(json_lines_viewer(&output)) as Arc<dyn Viewer>,
+			ViewerKind::Images => {
+				Arc::new(image_viewer(&output, &viewer_configuration, bounds, physics)) as Arc<dyn Viewer>
 			}
+			ViewerKind::Svg => Arc::new(
+				SvgViewer::new(
+					SvgRenderer::new(bounds, physics),
+					output.svg.as_ref().expect("Scenario was validated"),
+				)
+				.with_window(output.svg_window),
+			) as Arc<dyn Viewer>,
+		})
+		.collect::<Vec<_>>();
+	let viewer = if viewers.len() == 1 {
+		viewers.remove(0)
+	} else {
+		Arc::new(CompositeViewer::new(viewers).unwrap_or_else(|error| exit_with_error(&error)))
+	};
+
+	// Keep stdout machine-readable if the JSON lines are written to it
+	let summary_to_stderr = kinds.contains(&ViewerKind::JsonLines) && output.json_lines.is_none();
+	let report = move |scoreboard: Scoreboard| report_results(&scoreboard, summary_to_stderr, results_path.as_deref());
 
-			viewer.finished(&world);
+	// A viewer on the main thread may end the process when it is closed, so the results are reported as soon as
+	// the simulation is done. Otherwise, they are reported once the viewer is closed, so they end up after its output.
+	let report_on_simulation_thread = viewer.requires_main_thread();
+	let runner = runner.with_viewer(viewer.clone());
+	let simulation_handle = std::thread::spawn(move || {
+		let scoreboard = runner.run();
+		if report_on_simulation_thread {
+			report(scoreboard);
+			None
+		} else {
+			Some((scoreboard, report))
 		}
 	});
 
@@ -139,5 +418,69 @@ fn main() {
 	// That's why the simulation is spawned away and the Viewer runs on the main thread.
 	viewer.run();
 
-	simulation_handle.join().unwrap();
+	if let Some((scoreboard, report)) = simulation_handle.join().unwrap() {
+		report(scoreboard);
+	}
+}
+
+/// Print the summary of every round and write the results file, if any
+fn report_results(scoreboard: &Scoreboard, summary_to_stderr: bool, results_path: Option<&Path>) {
+	let print_summary = |summary: String| {
+		if summary_to_stderr {
+			eprintln!("{summary}");
+		} else {
+			println!("{summary}");
+		}
+	};
+	for (round, result) in scoreboard.rounds().iter().enumerate() {
+		print_summary(format!("Round {}:\n{}", round + 1, result));
+	}
+	if scoreboard.rounds().len() > 1 {
+		print_summary(scoreboard.to_string());
+	}
+
+	if let Some(path) = results_path {
+		let result = File::create(path).map_err(|error| error.to_string()).and_then(|file| {
+			serde_json::to_writer_pretty(BufWriter::new(file), scoreboard.rounds()).map_err(|error| error.to_string())
+		});
+		if let Err(error) = result {
+			eprintln!("Failed to write results to {}: {error}", path.display());
+		}
+	}
+}
+
+fn json_lines_viewer(output: &OutputConfiguration) -> JsonLinesViewer {
+	let viewer = match &output.json_lines {
+		Some(path) => File::create(path)
+			.map(|file| JsonLinesViewer::new(BufWriter::new(file)))
+			.unwrap_or_else(|error| exit_with_error(&format!("Failed to create {}: {error}", path.display()))),
+		None => JsonLinesViewer::new(BufWriter::new(std::io::stdout())),
+	};
+	viewer.with_interval(output.json_lines_interval)
+}
+
+fn image_viewer(
+	output: &OutputConfiguration,
+	viewer: &ViewerConfiguration,
+	bounds: Vector,
+	physics: Physics,
+) -> ImageViewer {
+	let path = output.images.as_ref().expect("Scenario was validated");
+	let renderer = FrameRenderer::new(bounds, physics).with_scale(output.image_scale);
+	// play the animation at the same speed as the simulation
+	let frame_delay = if viewer.ticks_per_second > 0.0 {
+		Duration::from_secs_f64(output.image_interval as f64 / viewer.ticks_per_second)
+	} else {
+		Duration::from_millis(50)
+	};
+
+	ImageViewer::new(renderer, output.image_format, path)
+		.unwrap_or_else(|error| exit_with_error(&error))
+		.with_interval(output.image_interval)
+		.with_frame_delay(frame_delay)
+}
+
+fn exit_with_error(message: &str) -> ! {
+	eprintln!("{message}");
+	std::process::exit(1)
 }
@@ -0,0 +1,11 @@
+use crate::id::Id;
+use serde::Serialize;
+
+/// Something noteworthy that happened during a step of the simulation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Event {
+	/// `tagger` tagged `tagged`, which is "it" from now on. The `iteration` is the one after the step, the same as
+	/// [`World::iteration`](crate::world::World::iteration) and [`WorldSnapshot::iteration`](crate::world::WorldSnapshot::iteration).
+	Tag { iteration: usize, tagger: Id, tagged: Id },
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Deserializer};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How the [`World`](crate::world::World) runs the behaviors of its agents in every step.
+///
+/// Written the same way on the command line and in scenarios: `sequential`, `parallel`, `chunked:<agents>` or
+/// `automatic`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutionStrategy {
+	/// One agent after the other on the current thread
+	Sequential,
+	/// Every agent is a separate rayon task, rayon splits them up as it sees fit
+	Parallel,
+	/// In parallel, but every rayon task simulates at least this many agents
+	Chunked(usize),
+	/// Measure how long steps take and switch to the fastest of the other strategies while the simulation runs
+	Automatic,
+}
+
+impl FromStr for ExecutionStrategy {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		use ExecutionStrategy::*;
+		match text {
+			"sequential" => Ok(Sequential),
+			"parallel" => Ok(Parallel),
+			"automatic" => Ok(Automatic),
+			_ => match text.strip_prefix("chunked:").map(str::parse) {
+				Some(Ok(chunk_size)) if chunk_size > 0 => Ok(Chunked(chunk_size)),
+				_ => Err(format!("Invalid execution strategy: {text}")),
+			},
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for ExecutionStrategy {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		String::deserialize(deserializer)?
+			.parse()
+			.map_err(serde::de::Error::custom)
+	}
+}
+
+/// Steps measured per candidate before choosing the fastest one
+const MEASURED_STEPS: usize = 5;
+/// Steps to run with the chosen strategy before measuring again, e.g. because the behaviors became more expensive
+const STEPS_BETWEEN_MEASUREMENTS: usize = 1000;
+/// The chosen strategy runs at least this many times as long as the measurements took before measuring again.
+/// Trying slow candidates, e.g. parallel execution for a few agents, then costs at most about 5% of the time.
+const CHOSEN_TIME_PER_MEASUREMENT_TIME: u32 = 20;
+/// Tasks per rayon thread that the chunk sizes are tuned for, more tasks balance uneven work better
+const TASKS_PER_THREAD: [usize; 2] = [1, 4];
+
+/// State of [`ExecutionStrategy::Automatic`]: Tries every candidate for a few steps, then sticks with the fastest one
+/// for a while before measuring again. The more time the measurements took compared to the chosen strategy, the longer
+/// it waits.
+#[derive(Clone, Debug)]
+pub(crate) struct AdaptiveExecution {
+	candidates: Vec<ExecutionStrategy>,
+	/// Fastest measured step of every candidate in this round of measurements
+	fastest_steps: Vec<Option<Duration>>,
+	/// Steps taken since the measurements started
+	step: usize,
+	chosen: ExecutionStrategy,
+	/// Time spent on the steps of this round of measurements
+	measurement_time: Duration,
+	/// Time spent on steps with the chosen strategy since
+	chosen_time: Duration,
+}
+
+impl AdaptiveExecution {
+	pub(crate) fn new(agent_count: usize, thread_count: usize) -> Self {
+		let mut candidates = vec![ExecutionStrategy::Sequential];
+		if thread_count > 1 {
+			candidates.push(ExecutionStrategy::Parallel);
+			for tasks_per_thread in TASKS_PER_THREAD {
+				let chunk_size = agent_count.div_ceil(thread_count * tasks_per_thread).max(1);
+				let candidate = ExecutionStrategy::Chunked(chunk_size);
+				if !candidates.contains(&candidate) {
+					candidates.push(candidate);
+				}
+			}
+		}
+
+		Self {
+			fastest_steps: vec![None; candidates.len()],
+			step: 0,
+			chosen: candidates[0],
+			candidates,
+			measurement_time: Duration::ZERO,
+			chosen_time: Duration::ZERO,
+		}
+	}
+
+	/// Strategy for the next step
+	pub(crate) fn next(&self) -> ExecutionStrategy {
+		match self.measuring() {
+			Some(index) => self.candidates[index],
+			None => self.chosen,
+		}
+	}
+
+	/// Remember how long the last step took with the strategy returned by [`AdaptiveExecution::next`]
+	pub(crate) fn record(&mut self, duration: Duration) {
+		if let Some(index) = self.measuring() {
+			let fastest = &mut self.fastest_steps[index];
+			*fastest = Some(fastest.map_or(duration, |fastest| fastest.min(duration)));
+			self.measurement_time += duration;
+		} else {
+			self.chosen_time += duration;
+		}
+
+		self.step += 1;
+		let measured_steps = self.candidates.len() * MEASURED_STEPS;
+		if self.step == measured_steps {
+			let (fastest_index, _) = self
+				.fastest_steps
+				.iter()
+				.enumerate()
+				.min_by_key(|(_, duration)| duration.unwrap_or(Duration::MAX))
+				.expect("There is always at least one candidate");
+			self.chosen = self.candidates[fastest_index];
+		}
+		if (self.step >= measured_steps + STEPS_BETWEEN_MEASUREMENTS)
+			&& (self.chosen_time >= self.measurement_time * CHOSEN_TIME_PER_MEASUREMENT_TIME)
+		{
+			self.step = 0;
+			self.fastest_steps.fill(None);
+			self.measurement_time = Duration::ZERO;
+			self.chosen_time = Duration::ZERO;
+		}
+	}
+
+	/// Index of the candidate that is being measured, if any
+	fn measuring(&self) -> Option<usize> {
+		let index = self.step / MEASURED_STEPS;
+		(index < self.candidates.len()).then_some(index)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_choose_the_fastest_strategy() {
+		let mut adaptive = AdaptiveExecution::new(1000, 4);
+		use ExecutionStrategy::*;
+		assert_eq!(
+			vec![Sequential, Parallel, Chunked(250), Chunked(63)],
+			adaptive.candidates
+		);
+
+		let durations = |strategy| match strategy {
+			Chunked(250) => Duration::from_micros(10),
+			_ => Duration::from_micros(20),
+		};
+		for _ in 0..(4 * MEASURED_STEPS) {
+			let strategy = adaptive.next();
+			adaptive.record(durations(strategy));
+		}
+		for _ in 0..STEPS_BETWEEN_MEASUREMENTS {
+			assert_eq!(Chunked(250), adaptive.next());
+			adaptive.record(Duration::from_micros(10));
+		}
+		// measures again
+		assert_eq!(Sequential, adaptive.next());
+		assert_eq!(vec![Sequential], AdaptiveExecution::new(1000, 1).candidates);
+	}
+
+	#[test]
+	fn should_measure_less_often_if_measuring_is_expensive() {
+		use ExecutionStrategy::*;
+		let mut adaptive = AdaptiveExecution::new(10, 4);
+		let durations = |strategy| match strategy {
+			Sequential => Duration::from_micros(1),
+			_ => Duration::from_micros(100),
+		};
+		let measured_steps = adaptive.candidates.len() * MEASURED_STEPS;
+		let measurement_time = MEASURED_STEPS as u32 * (durations(Sequential) + 3 * durations(Parallel));
+		let steps_until_measuring_again = (measurement_time * CHOSEN_TIME_PER_MEASUREMENT_TIME).as_micros() as usize;
+		assert!(steps_until_measuring_again > STEPS_BETWEEN_MEASUREMENTS);
+
+		for _ in 0..measured_steps {
+			let strategy = adaptive.next();
+			adaptive.record(durations(strategy));
+		}
+		for _ in 0..steps_until_measuring_again {
+			assert_eq!(None, adaptive.measuring());
+			assert_eq!(Sequential, adaptive.next());
+			adaptive.record(durations(Sequential));
+		}
+		// measures again
+		assert_eq!(Some(0), adaptive.measuring());
+	}
+
+	#[test]
+	fn should_deserialize_strategies_like_they_are_parsed() {
+		#[derive(Deserialize)]
+		struct Configuration {
+			execution: ExecutionStrategy,
+		}
+
+		let parse = |toml| toml::from_str::<Configuration>(toml).map(|configuration| configuration.execution);
+		assert_eq!(
+			ExecutionStrategy::Chunked(64),
+			parse("execution = \"chunked:64\"").unwrap()
+		);
+		assert_eq!(
+			ExecutionStrategy::Automatic,
+			parse("execution = \"automatic\"").unwrap()
+		);
+		assert!(parse("execution = \"chunked:0\"").is_err());
+	}
+
+	#[test]
+	fn should_parse_strategies() {
+		assert_eq!(Ok(ExecutionStrategy::Chunked(64)), "chunked:64".parse());
+		assert_eq!(Ok(ExecutionStrategy::Automatic), "automatic".parse());
+		assert!("chunked:0".parse::<ExecutionStrategy>().is_err());
+		assert!("fast".parse::<ExecutionStrategy>().is_err());
+	}
+}
@@ -0,0 +1,159 @@
+use crate::id::Id;
+use crate::statistics::Statistics;
+use crate::termination::TerminationReason;
+use crate::world::World;
+use serde::Serialize;
+use static_assertions::assert_obj_safe;
+use std::fmt::{Display, Formatter};
+
+/// The rules that decide who is doing well in a game of tag and when the game is decided.
+pub trait GameMode: Send + Sync {
+	/// Score of every agent, indexed by [`Id`]. Higher is better.
+	fn scores(&self, world: &World) -> Vec<f32>;
+
+	/// Has the game been decided? This is the victory condition of the game mode.
+	fn is_decided(&self, world: &World) -> bool;
+
+	/// The agent with the highest score. If multiple agents share the highest score, there is no winner.
+	fn winner(&self, world: &World) -> Option<Id> {
+		let scores = self.scores(world);
+		let (winner, best_score) = scores.iter().enumerate().max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+		let is_unique = scores.iter().filter(|&score| score == best_score).count() == 1;
+		is_unique.then(|| Id::from(winner))
+	}
+}
+
+assert_obj_safe!(GameMode);
+
+/// Classic game of tag: Nobody wants to be "it", so the less time an agent spends as "it", the better.
+///
+/// The game is decided once the tag limit is reached, without a tag limit it never is.
+#[derive(Default)]
+pub struct ClassicTag {
+	pub tag_limit: Option<usize>,
+}
+
+impl GameMode for ClassicTag {
+	fn scores(&self, world: &World) -> Vec<f32> {
+		world
+			.statistics()
+			.agents
+			.iter()
+			.map(|agent| 0.0 - agent.iterations_as_it as f32)
+			.collect()
+	}
+
+	fn is_decided(&self, world: &World) -> bool {
+		self.tag_limit
+			.map(|tag_limit| world.statistics().tag_count >= tag_limit)
+			.unwrap_or(false)
+	}
+}
+
+/// The outcome of a simulation run.
+#[derive(Clone, Serialize)]
+pub struct MatchResult {
+	pub winner: Option<Id>,
+	pub reason: TerminationReason,
+	pub iterations: usize,
+	/// Final scores according to the [`GameMode`], indexed by [`Id`]
+	pub scores: Vec<f32>,
+	pub statistics: Statistics,
+}
+
+impl MatchResult {
+	pub fn new(world: &World, game_mode: &dyn GameMode, reason: TerminationReason) -> Self {
+		Self {
+			winner: game_mode.winner(world),
+			reason,
+			iterations: world.iteration(),
+			scores: game_mode.scores(world),
+			statistics: world.statistics().clone(),
+		}
+	}
+}
+
+impl Display for MatchResult {
+	fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+		match self.winner {
+			Some(winner) => writeln!(formatter, "Winner: {winner}")?,
+			None => writeln!(formatter, "Winner: none")?,
+		}
+		writeln!(formatter, "Reason: {}", self.reason)?;
+		writeln!(formatter, "Iterations: {}", self.iterations)?;
+		writeln!(formatter, "Tags: {}", self.statistics.tag_count)?;
+		for (index, (agent, score)) in self.statistics.agents.iter().zip(&self.scores).enumerate() {
+			writeln!(
+				formatter,
+				"{}: Score: {:.2}, Tags: {}, Tagged: {}, Iterations as \"it\": {}",
+				Id::from(index),
+				score,
+				agent.tags,
+				agent.times_tagged,
+				agent.iterations_as_it,
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::behavior::default::DefaultBehavior;
+	use crate::types::Vector;
+	use rand::rngs::SmallRng;
+	use rand::SeedableRng;
+
+	/// Game mode with the same scores, whatever happens in the world
+	struct FixedScores(Vec<f32>);
+
+	impl GameMode for FixedScores {
+		fn scores(&self, _world: &World) -> Vec<f32> {
+			self.0.clone()
+		}
+
+		fn is_decided(&self, _world: &World) -> bool {
+			false
+		}
+	}
+
+	#[test]
+	fn should_find_the_winner_unless_the_best_score_is_shared() {
+		let world = World::random(
+			Vector::new(100.0, 100.0),
+			3,
+			DefaultBehavior::default,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		);
+
+		assert_eq!(Some(Id::from(1)), FixedScores(vec![-3.0, -1.0, -2.0]).winner(&world));
+		assert_eq!(None, FixedScores(vec![-1.0, -2.0, -1.0]).winner(&world));
+		assert_eq!(Some(Id::from(2)), FixedScores(vec![-2.0, -2.0, 0.0]).winner(&world));
+		assert_eq!(None, FixedScores(Vec::new()).winner(&world));
+	}
+
+	#[test]
+	fn should_score_less_time_as_it_higher_in_classic_tag() {
+		let mut world = World::random(
+			Vector::new(100.0, 100.0),
+			3,
+			DefaultBehavior::default,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		);
+		let game_mode = ClassicTag { tag_limit: Some(1) };
+		assert!(!game_mode.is_decided(&world));
+
+		world.simulate_step();
+
+		let it = usize::from(world.previous_it());
+		let scores = game_mode.scores(&world);
+		assert_eq!(-1.0, scores[it]);
+		assert_eq!(2, scores.iter().filter(|&&score| score == 0.0).count());
+		assert_eq!(world.statistics().tag_count >= 1, game_mode.is_decided(&world));
+	}
+}
@@ -1,8 +1,9 @@
+use serde::Serialize;
 use std::fmt::{Display, Formatter};
-use std::ops::Index;
+use std::ops::{Index, IndexMut};
 
 /// Opaque [`Id`] of an agent. This also gets used as an index into the array of agents in the [`World`]
-#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Serialize)]
 pub struct Id(usize);
 
 impl Display for Id {
@@ -30,3 +31,9 @@ impl<Element> Index<Id> for Vec<Element> {
 		self.index(id.0)
 	}
 }
+
+impl<Element> IndexMut<Id> for Vec<Element> {
+	fn index_mut(&mut self, id: Id) -> &mut Self::Output {
+		self.index_mut(id.0)
+	}
+}
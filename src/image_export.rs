@@ -0,0 +1,501 @@
+use crate::agent::Agent;
+use crate::id::Id;
+use crate::physics::Physics;
+use crate::types::{rotate_by_angle, Vector};
+use crate::viewer::Viewer;
+use crate::world::{World, WorldSnapshot};
+use serde::Deserialize;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Renders [`WorldSnapshot`]s into images in software, so it works without a display or GPU.
+///
+/// The images look like the [`BevyViewer`](crate::visualization::BevyViewer): Every agent is a square with
+/// a side length of twice its [`Physics::range`] on a grey background, "it" is red, the previous "it" green and all
+/// others black.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameRenderer {
+	bounds: Vector,
+	physics: Physics,
+	scale: f32,
+}
+
+/// An image where every pixel is an index into [`FrameRenderer::PALETTE`]
+pub struct IndexedImage {
+	pub width: u32,
+	pub height: u32,
+	pub pixels: Vec<u8>,
+}
+
+impl FrameRenderer {
+	/// RGB colors of the background, regular agents, "it" and the previous "it"
+	pub const PALETTE: [u8; 12] = [102, 102, 102, 0, 0, 0, 255, 0, 0, 0, 255, 0];
+	const BACKGROUND: u8 = 0;
+	const REGULAR: u8 = 1;
+	const IT: u8 = 2;
+	const PREVIOUS_IT: u8 = 3;
+
+	pub fn new(bounds: Vector, physics: Physics) -> Self {
+		Self {
+			bounds,
+			physics,
+			scale: 1.0,
+		}
+	}
+
+	/// Space around the playing field, so agents at the border are drawn completely
+	fn margin(&self) -> f32 {
+		1.5 * self.physics.range
+	}
+
+	/// Scale the image, a scale of 1 means one pixel per unit of the playing field
+	pub fn with_scale(mut self, scale: f32) -> Self {
+		self.scale = scale;
+		self
+	}
+
+	/// Width and height of the rendered images in pixels
+	pub fn size(&self) -> (u32, u32) {
+		let size = (self.bounds + Vector::splat(2.0 * self.margin())) * self.scale;
+		(size.x.round().max(1.0) as u32, size.y.round().max(1.0) as u32)
+	}
+
+	pub fn render(&self, snapshot: &WorldSnapshot) -> IndexedImage {
+		let (width, height) = self.size();
+		let mut image = IndexedImage {
+			width,
+			height,
+			pixels: vec![Self::BACKGROUND; width as usize * height as usize],
+		};
+
+		// draw "it" and the previous "it" last, so other agents don't hide them
+		let ids = (0..snapshot.agents.len())
+			.map(Id::from)
+			.filter(|&id| (id != snapshot.it) && (id != snapshot.previous_it))
+			.chain([snapshot.previous_it, snapshot.it]);
+		for id in ids {
+			let color = if id == snapshot.it {
+				Self::IT
+			} else if id == snapshot.previous_it {
+				Self::PREVIOUS_IT
+			} else {
+				Self::REGULAR
+			};
+			self.draw_agent(&mut image, &snapshot.agents[id], color);
+		}
+
+		image
+	}
+
+	/// Fill the square of an agent, rotated by its heading
+	fn draw_agent(&self, image: &mut IndexedImage, agent: &Agent, color: u8) {
+		let half_size = self.physics.range * self.scale;
+		// y points up in the simulation, but down in the image
+		let center = Vector::new(
+			(agent.position.x + self.margin()) * self.scale,
+			image.height as f32 - (agent.position.y + self.margin()) * self.scale,
+		);
+		// the bounding box of a square rotated by any angle
+		let extent = half_size * std::f32::consts::SQRT_2;
+		let columns = pixel_range(center.x - extent, center.x + extent, image.width);
+		let rows = pixel_range(center.y - extent, center.y + extent, image.height);
+
+		for row in rows {
+			for column in columns.clone() {
+				let pixel_center = Vector::new(column as f32 + 0.5, row as f32 + 0.5);
+				let offset = pixel_center - center;
+				// back into the coordinate system of the agent, where the square is axis aligned
+				let local = rotate_by_angle(Vector::new(offset.x, -offset.y), -agent.heading);
+				if (local.x.abs() <= half_size) && (local.y.abs() <= half_size) {
+					image.pixels[row as usize * image.width as usize + column as usize] = color;
+				}
+			}
+		}
+	}
+}
+
+fn pixel_range(start: f32, end: f32, size: u32) -> std::ops::Range<u32> {
+	let start = start.floor().max(0.0) as u32;
+	let end = (end.ceil().max(0.0) as u32).min(size);
+	start.min(end)..end
+}
+
+/// How the [`ImageViewer`] writes the images
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageFormat {
+	/// One PNG file per frame in a directory
+	Png,
+	/// One animated GIF of the whole run
+	Gif,
+	/// One animated PNG of the whole run
+	Apng,
+}
+
+impl FromStr for ImageFormat {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		use ImageFormat::*;
+		match text {
+			"png" => Ok(Png),
+			"gif" => Ok(Gif),
+			"apng" => Ok(Apng),
+			_ => Err(format!("Invalid image format: {text}")),
+		}
+	}
+}
+
+/// Viewer that renders every n-th iteration with a [`FrameRenderer`] and writes it to disk.
+///
+/// Every frame is rendered and written while the simulation runs. Animated PNGs declare their number of frames
+/// upfront, so it is filled in once the simulation is finished.
+pub struct ImageViewer {
+	renderer: FrameRenderer,
+	format: ImageFormat,
+	path: PathBuf,
+	interval: usize,
+	frame_delay: Duration,
+	state: Mutex<State>,
+}
+
+struct State {
+	frame_count: usize,
+	last_recorded_iteration: Option<usize>,
+	/// Only used for GIFs, `None` after finishing or failing
+	gif_encoder: Option<gif::Encoder<BufWriter<File>>>,
+	/// Only used for animated PNGs, created with the first frame, `None` after finishing or failing
+	apng_writer: Option<png::Writer<BufWriter<File>>>,
+	failed: bool,
+}
+
+impl ImageViewer {
+	/// Write images of the given format to `path`, which is a directory for [`ImageFormat::Png`] and a file otherwise
+	pub fn new(renderer: FrameRenderer, format: ImageFormat, path: &Path) -> Result<Self, String> {
+		let mut gif_encoder = None;
+		match format {
+			ImageFormat::Png => std::fs::create_dir_all(path)
+				.map_err(|error| format!("Failed to create {}: {error}", path.display()))?,
+			ImageFormat::Gif => gif_encoder = Some(create_gif_encoder(renderer, path)?),
+			ImageFormat::Apng => {
+				// create the file right away, so errors are noticed before the simulation runs
+				File::create(path).map_err(|error| format!("Failed to create {}: {error}", path.display()))?;
+			}
+		}
+
+		Ok(Self {
+			renderer,
+			format,
+			path: path.to_path_buf(),
+			interval: 1,
+			frame_delay: Duration::from_millis(50),
+			state: Mutex::new(State {
+				frame_count: 0,
+				last_recorded_iteration: None,
+				gif_encoder,
+				apng_writer: None,
+				failed: false,
+			}),
+		})
+	}
+
+	/// Only render every n-th iteration, the final iteration is always rendered
+	pub fn with_interval(mut self, interval: usize) -> Self {
+		self.interval = interval.max(1);
+		self
+	}
+
+	/// How long every frame of an animation is shown
+	pub fn with_frame_delay(mut self, frame_delay: Duration) -> Self {
+		self.frame_delay = frame_delay;
+		self
+	}
+
+	fn record(&self, world: &World, force: bool) {
+		let mut state = self.state.lock().expect("Lock was poisoned");
+		let iteration = world.iteration();
+		let is_due = iteration.is_multiple_of(self.interval);
+		if state.failed || !(is_due || force) || (state.last_recorded_iteration == Some(iteration)) {
+			return;
+		}
+		state.last_recorded_iteration = Some(iteration);
+
+		let result = match self.format {
+			ImageFormat::Png => self.write_png(&state, &world.snapshot()),
+			ImageFormat::Gif => self.write_gif_frame(&mut state, &world.snapshot()),
+			ImageFormat::Apng => self.write_apng_frame(&mut state, &world.snapshot()),
+		};
+		state.frame_count += 1;
+		state.report(result);
+	}
+
+	fn write_png(&self, state: &State, snapshot: &WorldSnapshot) -> Result<(), String> {
+		let path = self.path.join(format!("frame-{:06}.png", state.frame_count));
+		let file = File::create(&path).map_err(|error| format!("Failed to create {}: {error}", path.display()))?;
+		write_png(BufWriter::new(file), &self.renderer.render(snapshot))
+	}
+
+	fn write_apng_frame(&self, state: &mut State, snapshot: &WorldSnapshot) -> Result<(), String> {
+		let image = self.renderer.render(snapshot);
+		let writer = match &mut state.apng_writer {
+			Some(writer) => writer,
+			None => state
+				.apng_writer
+				.insert(create_apng_writer(self.renderer, &self.path, self.frame_delay)?),
+		};
+		writer
+			.write_image_data(&image.pixels)
+			.map_err(|error| error.to_string())
+	}
+
+	fn write_gif_frame(&self, state: &mut State, snapshot: &WorldSnapshot) -> Result<(), String> {
+		let image = self.renderer.render(snapshot);
+		let frame = gif::Frame {
+			width: image.width as u16,
+			height: image.height as u16,
+			// in units of 10ms
+			delay: (self.frame_delay.as_millis() / 10).min(u16::MAX as u128) as u16,
+			buffer: image.pixels.into(),
+			..Default::default()
+		};
+		match &mut state.gif_encoder {
+			Some(encoder) => encoder.write_frame(&frame).map_err(|error| error.to_string()),
+			None => Ok(()),
+		}
+	}
+
+	fn finish(&self, state: &mut State) -> Result<(), String> {
+		match self.format {
+			ImageFormat::Png => Ok(()),
+			// the GIF trailer is written when the encoder is dropped
+			ImageFormat::Gif => {
+				state.gif_encoder.take();
+				Ok(())
+			}
+			ImageFormat::Apng => match state.apng_writer.take() {
+				Some(writer) => {
+					writer.finish().map_err(|error| error.to_string())?;
+					patch_frame_count(&self.path, state.frame_count as u32)
+						.map_err(|error| format!("Failed to write {}: {error}", self.path.display()))
+				}
+				None => Ok(()),
+			},
+		}
+	}
+}
+
+impl State {
+	/// Report the first error, nothing is written anymore after that
+	fn report(&mut self, result: Result<(), String>) {
+		if let Err(error) = result {
+			eprintln!("Failed to write images: {error}");
+			self.failed = true;
+			self.gif_encoder = None;
+			self.apng_writer = None;
+		}
+	}
+}
+
+impl Viewer for ImageViewer {
+	fn iteration(&self, world: &World) {
+		self.record(world, false);
+	}
+
+	fn finished(&self, world: &World) {
+		self.record(world, true);
+
+		let mut state = self.state.lock().expect("Lock was poisoned");
+		if !state.failed {
+			let result = self.finish(&mut state);
+			state.report(result);
+		}
+	}
+
+	fn run(&self) {
+		// Nothing to do, everything is written from the simulation thread
+	}
+}
+
+fn create_gif_encoder(renderer: FrameRenderer, path: &Path) -> Result<gif::Encoder<BufWriter<File>>, String> {
+	let (width, height) = renderer.size();
+	if (width > u16::MAX as u32) || (height > u16::MAX as u32) {
+		return Err(format!("The image is too large for a GIF: {width}x{height}"));
+	}
+
+	let file = File::create(path).map_err(|error| format!("Failed to create {}: {error}", path.display()))?;
+	let mut encoder = gif::Encoder::new(
+		BufWriter::new(file),
+		width as u16,
+		height as u16,
+		&FrameRenderer::PALETTE,
+	)
+	.map_err(|error| error.to_string())?;
+	encoder
+		.set_repeat(gif::Repeat::Infinite)
+		.map_err(|error| error.to_string())?;
+	Ok(encoder)
+}
+
+/// Encoder for images of the given size with the colors of [`FrameRenderer::PALETTE`]
+fn png_encoder<W: Write>(writer: W, width: u32, height: u32) -> png::Encoder<'static, W> {
+	let mut encoder = png::Encoder::new(writer, width, height);
+	encoder.set_color(png::ColorType::Indexed);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.set_palette(FrameRenderer::PALETTE.to_vec());
+	encoder
+}
+
+/// Write a single image as PNG
+fn write_png(writer: impl Write, image: &IndexedImage) -> Result<(), String> {
+	let mut writer = png_encoder(writer, image.width, image.height)
+		.write_header()
+		.map_err(|error| error.to_string())?;
+	writer
+		.write_image_data(&image.pixels)
+		.map_err(|error| error.to_string())?;
+	writer.finish().map_err(|error| error.to_string())
+}
+
+/// Number of frames an animated PNG declares while it is written, the actual one is filled in by
+/// [`patch_frame_count`]. The maximum allowed by the specification, so the encoder accepts every frame.
+const PLACEHOLDER_FRAME_COUNT: u32 = i32::MAX as u32;
+
+fn create_apng_writer(
+	renderer: FrameRenderer,
+	path: &Path,
+	frame_delay: Duration,
+) -> Result<png::Writer<BufWriter<File>>, String> {
+	let (width, height) = renderer.size();
+	let file = File::create(path).map_err(|error| format!("Failed to create {}: {error}", path.display()))?;
+	let mut encoder = png_encoder(BufWriter::new(file), width, height);
+	encoder
+		.set_animated(PLACEHOLDER_FRAME_COUNT, 0)
+		.map_err(|error| error.to_string())?;
+	let milliseconds = frame_delay.as_millis().min(u16::MAX as u128) as u16;
+	encoder
+		.set_frame_delay(milliseconds, 1000)
+		.map_err(|error| error.to_string())?;
+	encoder.write_header().map_err(|error| error.to_string())
+}
+
+/// Overwrite the number of frames in the animation control chunk of the given animated PNG
+fn patch_frame_count(path: &Path, frame_count: u32) -> std::io::Result<()> {
+	let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+	// skip the PNG signature, then every chunk is its length, type, data and checksum
+	let mut position = 8;
+	loop {
+		let mut header = [0; 8];
+		file.seek(SeekFrom::Start(position))?;
+		file.read_exact(&mut header)?;
+		let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+		let chunk_type = &header[4..];
+		if chunk_type == b"acTL" {
+			// the number of frames, followed by the number of plays
+			let mut data = [0; 8];
+			file.read_exact(&mut data)?;
+			data[..4].copy_from_slice(&frame_count.to_be_bytes());
+
+			let mut checksum = crc32fast::Hasher::new();
+			checksum.update(chunk_type);
+			checksum.update(&data);
+			file.seek(SeekFrom::Start(position + 8))?;
+			file.write_all(&data)?;
+			return file.write_all(&checksum.finalize().to_be_bytes());
+		}
+		position += 12 + u64::from(length);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::behavior::default::DefaultBehavior;
+	use rand::rngs::SmallRng;
+	use rand::SeedableRng;
+	use std::sync::Arc;
+
+	fn snapshot(agents: Vec<Agent>) -> WorldSnapshot {
+		WorldSnapshot {
+			agents: Arc::new(agents),
+			iteration: 0,
+			it: Id::from(0),
+			previous_it: Id::from(1),
+			events: Vec::new(),
+			targets: Default::default(),
+		}
+	}
+
+	#[test]
+	fn should_render_agents_with_their_colors() {
+		let renderer = FrameRenderer::new(Vector::new(100.0, 100.0), Physics::default());
+		let agents = vec![
+			Agent {
+				position: Vector::new(20.0, 20.0),
+				heading: 0.0,
+			},
+			Agent {
+				position: Vector::new(80.0, 80.0),
+				heading: 1.0,
+			},
+			Agent {
+				position: Vector::new(80.0, 20.0),
+				heading: 2.0,
+			},
+		];
+
+		let image = renderer.render(&snapshot(agents));
+
+		assert_eq!((130, 130), (image.width, image.height));
+		let pixel = |x: f32, y: f32| {
+			let column = (x + renderer.margin()) as usize;
+			let row = image.height as usize - 1 - (y + renderer.margin()) as usize;
+			image.pixels[row * image.width as usize + column]
+		};
+		assert_eq!(FrameRenderer::IT, pixel(20.0, 20.0));
+		assert_eq!(FrameRenderer::PREVIOUS_IT, pixel(80.0, 80.0));
+		assert_eq!(FrameRenderer::REGULAR, pixel(80.0, 20.0));
+		assert_eq!(FrameRenderer::BACKGROUND, pixel(50.0, 50.0));
+	}
+
+	#[test]
+	fn should_write_animated_png_while_running() {
+		let mut world = World::random(
+			Vector::new(50.0, 50.0),
+			2,
+			DefaultBehavior::default,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		);
+		let physics = Physics {
+			range: 5.0,
+			..Default::default()
+		};
+		let renderer = FrameRenderer::new(world.bounds(), physics);
+		assert_eq!((65, 65), renderer.size());
+		let path = std::env::temp_dir().join(format!("tag-animated-{}.png", std::process::id()));
+		let viewer = ImageViewer::new(renderer, ImageFormat::Apng, &path).unwrap();
+
+		for _ in 0..3 {
+			world.simulate_step();
+			viewer.iteration(&world);
+		}
+		assert!(viewer.state.lock().unwrap().apng_writer.is_some());
+		viewer.finished(&world);
+
+		let decoder = png::Decoder::new(File::open(&path).unwrap());
+		let mut reader = decoder.read_info().unwrap();
+		assert_eq!(
+			Some(3),
+			reader.info().animation_control.map(|control| control.num_frames)
+		);
+		let mut buffer = vec![0; reader.output_buffer_size()];
+		for _ in 0..3 {
+			reader.next_frame(&mut buffer).unwrap();
+		}
+		std::fs::remove_file(&path).unwrap();
+	}
+}
@@ -0,0 +1,257 @@
+use crate::event::Event;
+use crate::id::Id;
+use crate::types::Radians;
+use crate::viewer::Viewer;
+use crate::world::World;
+use serde::Serialize;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Viewer that writes one JSON object per line for every recorded iteration, e.g. for analyzing runs with other tools.
+///
+/// Every record contains the round (starting at 0) and the iteration within it, the positions and headings
+/// (in radians) of all agents, "it", the previous "it" and all events since the previous record of the round, so no
+/// events are lost when only every n-th iteration is recorded:
+/// ```json
+/// {"round":0,"iteration":1,"it":3,"previous_it":3,"agents":[{"x":10.0,"y":20.0,"heading":1.57}],"events":[]}
+/// ```
+/// Every round is recorded from its initial state at iteration 0 up to its final iteration. Iterations are counted
+/// the same everywhere: The iteration of a record is the one of the state after the step, and so is the iteration of
+/// the events in it.
+pub struct JsonLinesViewer {
+	interval: usize,
+	state: Mutex<State>,
+}
+
+struct State {
+	/// `None` once writing has failed, so the error is only reported once
+	writer: Option<Box<dyn Write + Send>>,
+	pending_events: Vec<Event>,
+	/// Round and iteration whose events were last added to the pending ones, the viewer sees every state several times
+	last_seen: Option<(usize, usize)>,
+	/// Round and iteration of the last record
+	last_recorded: Option<(usize, usize)>,
+}
+
+#[derive(Serialize)]
+struct Record<'events> {
+	round: usize,
+	iteration: usize,
+	it: Id,
+	previous_it: Id,
+	agents: Vec<AgentRecord>,
+	events: &'events [Event],
+}
+
+#[derive(Serialize)]
+struct AgentRecord {
+	x: f32,
+	y: f32,
+	heading: Radians,
+}
+
+impl JsonLinesViewer {
+	/// Record every iteration to the given writer, which should be buffered
+	pub fn new(writer: impl Write + Send + 'static) -> Self {
+		Self {
+			interval: 1,
+			state: Mutex::new(State {
+				writer: Some(Box::new(writer)),
+				pending_events: Vec::new(),
+				last_seen: None,
+				last_recorded: None,
+			}),
+		}
+	}
+
+	/// Only record every n-th iteration, the initial and the final iteration of every round are always recorded
+	pub fn with_interval(mut self, interval: usize) -> Self {
+		self.interval = interval.max(1);
+		self
+	}
+
+	fn record(&self, world: &World, force: bool) {
+		let mut state = self.state.lock().expect("Lock was poisoned");
+		let (round, iteration) = (world.round(), world.iteration());
+		if state.last_seen != Some((round, iteration)) {
+			state.last_seen = Some((round, iteration));
+			state.pending_events.extend_from_slice(world.events());
+		}
+
+		let is_due = iteration.is_multiple_of(self.interval);
+		let is_recorded = state.last_recorded == Some((round, iteration));
+		if !(is_due || force) || is_recorded {
+			return;
+		}
+
+		let record = Record {
+			round,
+			iteration,
+			it: world.it(),
+			previous_it: world.previous_it(),
+			agents: world
+				.agents()
+				.iter()
+				.map(|agent| AgentRecord {
+					x: agent.position.x,
+					y: agent.position.y,
+					heading: agent.heading,
+				})
+				.collect(),
+			events: &state.pending_events,
+		};
+		let line = serde_json::to_string(&record).expect("Records can always be serialized");
+
+		state.pending_events.clear();
+		state.last_recorded = Some((round, iteration));
+		state.write(|writer| writeln!(writer, "{line}"));
+	}
+}
+
+impl State {
+	fn write(&mut self, write: impl FnOnce(&mut dyn Write) -> std::io::Result<()>) {
+		if let Some(writer) = &mut self.writer {
+			if let Err(error) = write(writer.as_mut()) {
+				eprintln!("Failed to write JSON lines: {error}");
+				self.writer = None;
+			}
+		}
+	}
+}
+
+impl Viewer for JsonLinesViewer {
+	fn iteration(&self, world: &World) {
+		self.record(world, false);
+	}
+
+	fn round_started(&self, world: &World) {
+		// events that weren't recorded yet belong to the previous round
+		self.state.lock().expect("Lock was poisoned").pending_events.clear();
+		self.record(world, true);
+	}
+
+	fn round_finished(&self, world: &World) {
+		self.record(world, true);
+	}
+
+	fn finished(&self, world: &World) {
+		self.record(world, true);
+		self.state
+			.lock()
+			.expect("Lock was poisoned")
+			.write(|writer| writer.flush());
+	}
+
+	fn run(&self) {
+		// Nothing to do, everything is written from the simulation thread
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::behavior::default::DefaultBehavior;
+	use crate::types::Vector;
+	use rand::rngs::SmallRng;
+	use rand::SeedableRng;
+	use std::sync::Arc;
+
+	/// Writer that can still be read after it was moved into the viewer
+	#[derive(Clone, Default)]
+	struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+	impl Write for SharedBuffer {
+		fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+			self.0.lock().unwrap().write(buffer)
+		}
+
+		fn flush(&mut self) -> std::io::Result<()> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn should_record_every_nth_iteration_and_the_first_and_last_one_of_every_round() {
+		let mut random_generator = SmallRng::seed_from_u64(42);
+		let mut world = World::random(
+			Vector::new(100.0, 100.0),
+			3,
+			DefaultBehavior::default,
+			false,
+			&mut random_generator,
+		);
+		let buffer = SharedBuffer::default();
+		let viewer = JsonLinesViewer::new(buffer.clone()).with_interval(3);
+
+		for round in 0..2 {
+			if round > 0 {
+				world.randomize(&mut random_generator);
+			}
+			viewer.round_started(&world);
+			for _ in 0..7 {
+				world.simulate_step();
+				viewer.iteration(&world);
+			}
+			viewer.round_finished(&world);
+		}
+		viewer.finished(&world);
+
+		let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+		let records = output
+			.lines()
+			.map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+			.collect::<Vec<_>>();
+		let iterations = records
+			.iter()
+			.map(|record| (record["round"].as_u64().unwrap(), record["iteration"].as_u64().unwrap()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			vec![(0, 0), (0, 3), (0, 6), (0, 7), (1, 0), (1, 3), (1, 6), (1, 7)],
+			iterations
+		);
+		assert_eq!(3, records[0]["agents"].as_array().unwrap().len());
+		assert!(records[0]["events"].is_array());
+	}
+
+	#[test]
+	fn should_record_the_events_of_the_last_step_once() {
+		let mut random_generator = SmallRng::seed_from_u64(42);
+		let mut world = World::random(
+			Vector::new(100.0, 100.0),
+			2,
+			DefaultBehavior::default,
+			false,
+			&mut random_generator,
+		);
+		// "it" is on top of the other agent, so it tags it in the first step of the next round
+		let agents = vec![world.agents()[0].clone(); 2];
+		world.reset(agents, Id::from(0));
+		let buffer = SharedBuffer::default();
+		let viewer = JsonLinesViewer::new(buffer.clone());
+
+		viewer.round_started(&world);
+		world.simulate_step();
+		assert_eq!(1, world.events().len());
+		viewer.iteration(&world);
+		viewer.round_finished(&world);
+		world.randomize(&mut random_generator);
+		viewer.round_started(&world);
+		viewer.round_finished(&world);
+		viewer.finished(&world);
+
+		let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+		let events = output
+			.lines()
+			.map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+			.map(|record| {
+				(
+					record["round"].as_u64().unwrap(),
+					record["iteration"].as_u64().unwrap(),
+					record["events"].as_array().unwrap().len(),
+				)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(vec![(1, 0, 0), (1, 1, 1), (2, 0, 0)], events);
+		assert!(output.contains(r#""events":[{"type":"tag","iteration":1,"tagger":0,"tagged":1}]"#));
+	}
+}
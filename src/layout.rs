@@ -0,0 +1,286 @@
+use crate::agent::{Agent, AgentRelationShip};
+use crate::id::Id;
+use crate::physics::Physics;
+use crate::types::{normalize_signed_radians, Radians, Vector};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+/// How a [`World`](crate::world::World) stores its agents for the queries of [`WorldView`](crate::world::WorldView).
+///
+/// The layout only affects performance, the results of the queries are the same.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AgentLayout {
+	/// One [`Agent`] after another, relations are calculated one pair at a time with [`Agent::relate_to`]
+	#[default]
+	ArrayOfStructs,
+	/// Additionally keep the agents in [`AgentColumns`] when they are queried in a step, relations to all agents are
+	/// calculated at once
+	StructOfArrays,
+}
+
+impl FromStr for AgentLayout {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		use AgentLayout::*;
+		match text {
+			"array-of-structs" => Ok(ArrayOfStructs),
+			"struct-of-arrays" => Ok(StructOfArrays),
+			_ => Err(format!("Invalid agent layout: {text}")),
+		}
+	}
+}
+
+/// Agents with every field in a separate array, indexed by [`Id`].
+///
+/// The kernels calculate something for all agents at once, in tight loops over the arrays. The offsets and distances
+/// are plain arithmetic, the viewing angles call `atan2` once per agent on the precomputed offsets.
+#[derive(Clone, Default)]
+pub struct AgentColumns {
+	xs: Vec<f32>,
+	ys: Vec<f32>,
+	headings: Vec<f32>,
+}
+
+impl AgentColumns {
+	/// Replace the stored agents, keeping the allocations
+	pub fn fill(&mut self, agents: &[Agent]) {
+		self.xs.clear();
+		self.ys.clear();
+		self.headings.clear();
+		self.xs.extend(agents.iter().map(|agent| agent.position.x));
+		self.ys.extend(agents.iter().map(|agent| agent.position.y));
+		self.headings.extend(agents.iter().map(|agent| agent.heading));
+	}
+
+	pub fn len(&self) -> usize {
+		self.xs.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.xs.is_empty()
+	}
+
+	pub fn agent(&self, id: Id) -> Agent {
+		let index = usize::from(id);
+		Agent {
+			position: Vector::new(self.xs[index], self.ys[index]),
+			heading: self.headings[index],
+		}
+	}
+
+	/// Kernel: Offsets of all agents from the given position
+	pub fn offsets(&self, from: Vector, dxs: &mut Vec<f32>, dys: &mut Vec<f32>) {
+		dxs.clear();
+		dys.clear();
+		dxs.extend(self.xs.iter().map(|&x| x - from.x));
+		dys.extend(self.ys.iter().map(|&y| y - from.y));
+	}
+
+	/// Relationships to all agents that the given one sees, the same as filtering [`Agent::relate_to`] with
+	/// [`Physics::is_visible`]
+	pub fn visible_relationships(&self, viewed_by: Id, physics: &Physics) -> VisibleRelationships {
+		let viewer = self.agent(viewed_by);
+		let mut scratch = Scratch::take();
+		self.offsets(viewer.position, &mut scratch.dxs, &mut scratch.dys);
+		distances(&scratch.dxs, &scratch.dys, &mut scratch.distances);
+		viewing_angles(&scratch.dxs, &scratch.dys, viewer.heading, &mut scratch.angles);
+
+		VisibleRelationships {
+			scratch,
+			viewed_by,
+			physics: *physics,
+			index: 0,
+		}
+	}
+}
+
+impl From<&[Agent]> for AgentColumns {
+	fn from(agents: &[Agent]) -> Self {
+		let mut columns = Self::default();
+		columns.fill(agents);
+		columns
+	}
+}
+
+/// Kernel: Lengths of the given offsets, the distances for [`AgentColumns::offsets`]
+pub fn distances(dxs: &[f32], dys: &[f32], distances: &mut Vec<f32>) {
+	distances.clear();
+	distances.extend(dxs.iter().zip(dys).map(|(&dx, &dy)| (dx * dx + dy * dy).sqrt()));
+}
+
+/// Kernel: At what angle an agent with the given heading sees the given offsets, the same as [`Agent::viewing_angle`]
+pub fn viewing_angles(dxs: &[f32], dys: &[f32], heading: Radians, angles: &mut Vec<f32>) {
+	angles.clear();
+	angles.extend(dxs.iter().zip(dys).map(|(&dx, &dy)| {
+		let angle = normalize_signed_radians(dy.atan2(dx) - heading);
+		// identical positions are seen straight ahead
+		if (dx == 0.0) && (dy == 0.0) {
+			0.0
+		} else {
+			angle
+		}
+	}));
+}
+
+/// Buffers the kernels write into, reused by the queries on the same thread instead of allocating new ones
+#[derive(Default)]
+struct Scratch {
+	dxs: Vec<f32>,
+	dys: Vec<f32>,
+	distances: Vec<f32>,
+	angles: Vec<f32>,
+}
+
+thread_local! {
+	/// A stack, because queries can overlap, e.g. when a behavior asks for something else while iterating
+	static SCRATCH: RefCell<Vec<Scratch>> = RefCell::default();
+}
+
+impl Scratch {
+	fn take() -> Self {
+		SCRATCH
+			.try_with(|scratch| scratch.borrow_mut().pop())
+			.ok()
+			.flatten()
+			.unwrap_or_default()
+	}
+
+	fn give_back(self) {
+		// the thread may already be shutting down, then the buffers are simply dropped
+		let _ = SCRATCH.try_with(|scratch| scratch.borrow_mut().push(self));
+	}
+}
+
+/// Iterator returned by [`AgentColumns::visible_relationships`]
+pub struct VisibleRelationships {
+	scratch: Scratch,
+	viewed_by: Id,
+	physics: Physics,
+	index: usize,
+}
+
+impl Iterator for VisibleRelationships {
+	type Item = (Id, AgentRelationShip);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while self.index < self.scratch.distances.len() {
+			let index = self.index;
+			self.index += 1;
+			let id = Id::from(index);
+			let relationship = AgentRelationShip {
+				distance: self.scratch.distances[index],
+				direction: self.scratch.angles[index],
+			};
+			if (id != self.viewed_by) && self.physics.is_visible(&relationship) {
+				return Some((id, relationship));
+			}
+		}
+		None
+	}
+}
+
+impl Drop for VisibleRelationships {
+	fn drop(&mut self) {
+		std::mem::take(&mut self.scratch).give_back();
+	}
+}
+
+/// [`AgentColumns`] that are only filled once somebody queries them, so steps without queries don't copy the agents
+#[derive(Default)]
+pub(crate) struct LazyColumns {
+	columns: OnceLock<AgentColumns>,
+	/// The columns of the previous step, to keep their allocations
+	spare: Mutex<AgentColumns>,
+}
+
+impl LazyColumns {
+	/// The columns of the given agents, which have to be the same ones until [`LazyColumns::invalidate`] is called
+	pub(crate) fn get(&self, agents: &[Agent]) -> &AgentColumns {
+		self.columns.get_or_init(|| {
+			let mut columns = std::mem::take(&mut *self.spare.lock().expect("Lock was poisoned"));
+			columns.fill(agents);
+			columns
+		})
+	}
+
+	/// The agents changed, fill the columns again on the next query
+	pub(crate) fn invalidate(&mut self) {
+		if let Some(columns) = self.columns.take() {
+			*self.spare.get_mut().expect("Lock was poisoned") = columns;
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use rand::rngs::SmallRng;
+	use rand::SeedableRng;
+	use std::f32::consts::PI;
+
+	#[test]
+	fn should_see_the_same_agents_as_relate_to() {
+		let mut random_generator = SmallRng::seed_from_u64(42);
+		let bounds = Vector::new(100.0, 100.0);
+		let mut agents = (0..500)
+			.map(|_| Agent::random(bounds, &mut random_generator))
+			.collect::<Vec<_>>();
+		// an agent on top of another one is seen as straight ahead
+		agents.push(agents[0].clone());
+		let columns = AgentColumns::from(agents.as_slice());
+
+		for physics in [
+			Physics::default(),
+			Physics {
+				field_of_view_angle: PI / 3.0,
+				..Default::default()
+			},
+		] {
+			for viewed_by in 0..agents.len() {
+				let expected = agents
+					.iter()
+					.enumerate()
+					.filter(|&(index, _)| index != viewed_by)
+					.map(|(index, other)| (index, agents[viewed_by].relate_to(other)))
+					.filter(|(_, relationship)| physics.is_visible(relationship))
+					.map(|(index, relationship)| (index, relationship.distance, relationship.direction))
+					.collect::<Vec<_>>();
+				let actual = columns
+					.visible_relationships(Id::from(viewed_by), &physics)
+					.map(|(id, relationship)| (usize::from(id), relationship.distance, relationship.direction))
+					.collect::<Vec<_>>();
+
+				assert_eq!(expected, actual);
+			}
+		}
+	}
+
+	#[test]
+	fn should_fill_the_columns_again_after_invalidating() {
+		let mut random_generator = SmallRng::seed_from_u64(42);
+		let bounds = Vector::new(100.0, 100.0);
+		let agents = (0..10)
+			.map(|_| Agent::random(bounds, &mut random_generator))
+			.collect::<Vec<_>>();
+		let moved_agents = (0..10)
+			.map(|_| Agent::random(bounds, &mut random_generator))
+			.collect::<Vec<_>>();
+		let mut columns = LazyColumns::default();
+
+		assert_eq!(agents[3].position, columns.get(&agents).agent(Id::from(3)).position);
+		// the columns stay the same until they are invalidated
+		assert_eq!(
+			agents[3].position,
+			columns.get(&moved_agents).agent(Id::from(3)).position
+		);
+		columns.invalidate();
+		assert_eq!(
+			moved_agents[3].position,
+			columns.get(&moved_agents).agent(Id::from(3)).position
+		);
+	}
+}
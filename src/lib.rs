@@ -1,6 +1,23 @@
 pub mod agent;
 pub mod behavior;
+pub mod event;
+pub mod execution;
+pub mod game;
 pub mod id;
+pub mod image_export;
+pub mod json_lines;
+pub mod layout;
+pub mod match_runner;
+pub mod physics;
+pub mod runner;
+pub mod scenario;
+pub mod shard;
+pub mod statistics;
+pub mod svg;
+pub mod sweep;
+pub mod terminal;
+pub mod termination;
+pub mod tournament;
 pub mod types;
 pub mod viewer;
 pub mod visualization;
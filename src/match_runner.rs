@@ -0,0 +1,291 @@
+use crate::game::{GameMode, MatchResult};
+use crate::id::Id;
+use crate::termination::{TerminationCondition, TerminationReason};
+use crate::world::World;
+use rand::Rng;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Plays multiple rounds of a [`GameMode`] in the same [`World`].
+///
+/// The behavior instances are kept between rounds, so any learning state they have carries over.
+pub struct MatchRunner {
+	game_mode: Arc<dyn GameMode>,
+	rounds: usize,
+	round_start: RoundStart,
+}
+
+/// How the agents are placed at the start of every round
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundStart {
+	/// Every round starts with the same positions and "it" as the first one
+	Reset,
+	/// Every round after the first one starts with random positions and a random "it"
+	#[default]
+	Randomize,
+}
+
+impl FromStr for RoundStart {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		use RoundStart::*;
+		match text {
+			"reset" => Ok(Reset),
+			"randomize" => Ok(Randomize),
+			_ => Err(format!("Invalid round start: {text}")),
+		}
+	}
+}
+
+impl MatchRunner {
+	pub fn new(game_mode: Arc<dyn GameMode>, rounds: usize, round_start: RoundStart) -> Self {
+		Self {
+			game_mode,
+			rounds,
+			round_start,
+		}
+	}
+
+	/// Play all rounds. The termination condition for every round is created by the given constructor.
+	pub fn run(
+		&self,
+		world: &mut World,
+		mut termination_constructor: impl FnMut() -> Box<dyn TerminationCondition>,
+		random_generator: &mut impl Rng,
+	) -> Scoreboard {
+		self.run_rounds(world, random_generator, |world| {
+			world.simulate_until(termination_constructor().as_mut())
+		})
+	}
+
+	/// Play all rounds, every round is played by the given function. This places the agents at the start of every
+	/// round and keeps the score, but leaves stepping the world to the caller, e.g. to pace it or show it in a viewer.
+	///
+	/// No further rounds are played once a round was [`TerminationReason::Stopped`].
+	pub fn run_rounds(
+		&self,
+		world: &mut World,
+		random_generator: &mut impl Rng,
+		mut play_round: impl FnMut(&mut World) -> TerminationReason,
+	) -> Scoreboard {
+		let initial_agents = world.agents().to_vec();
+		let initial_it = world.it();
+		let mut scoreboard = Scoreboard::new(world.behavior_names());
+
+		for round in 0..self.rounds {
+			if round > 0 {
+				match self.round_start {
+					RoundStart::Reset => world.reset(initial_agents.clone(), initial_it),
+					RoundStart::Randomize => world.randomize(random_generator),
+				}
+			}
+
+			let reason = play_round(world);
+			scoreboard.add_round(MatchResult::new(world, self.game_mode.as_ref(), reason));
+			if reason == TerminationReason::Stopped {
+				break;
+			}
+		}
+
+		scoreboard
+	}
+}
+
+/// Accumulated scores of multiple rounds, per agent and per behavior.
+pub struct Scoreboard {
+	behavior_names: Vec<&'static str>,
+	rounds: Vec<MatchResult>,
+}
+
+impl Scoreboard {
+	/// Create an empty scoreboard for agents with the given behavior names, indexed by [`Id`]
+	pub fn new(behavior_names: Vec<&'static str>) -> Self {
+		Self {
+			behavior_names,
+			rounds: Vec::new(),
+		}
+	}
+
+	pub fn add_round(&mut self, result: MatchResult) {
+		self.rounds.push(result);
+	}
+
+	/// Results of all rounds played so far
+	pub fn rounds(&self) -> &[MatchResult] {
+		&self.rounds
+	}
+
+	/// Scores per agent, ranked from best to worst
+	pub fn agent_ranking(&self) -> Vec<AgentScore> {
+		let mut scores = self
+			.behavior_names
+			.iter()
+			.enumerate()
+			.map(|(index, &behavior)| {
+				let id = Id::from(index);
+				AgentScore {
+					id,
+					behavior,
+					score: self.rounds.iter().map(|round| round.scores[index]).sum(),
+					wins: self.rounds.iter().filter(|round| round.winner == Some(id)).count(),
+				}
+			})
+			.collect::<Vec<_>>();
+
+		scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+		scores
+	}
+
+	/// Scores per behavior, ranked from best to worst by the average score of their agents.
+	pub fn behavior_ranking(&self) -> Vec<BehaviorScore> {
+		let mut scores = Vec::<BehaviorScore>::new();
+		for agent_score in self.agent_ranking() {
+			let behavior_score = match scores.iter_mut().find(|score| score.behavior == agent_score.behavior) {
+				Some(behavior_score) => behavior_score,
+				None => {
+					scores.push(BehaviorScore {
+						behavior: agent_score.behavior,
+						agent_count: 0,
+						score: 0.0,
+						wins: 0,
+					});
+					scores.last_mut().unwrap() // safe because we just pushed it
+				}
+			};
+
+			behavior_score.agent_count += 1;
+			behavior_score.score += agent_score.score;
+			behavior_score.wins += agent_score.wins;
+		}
+
+		scores.sort_by(|a, b| b.average_score().total_cmp(&a.average_score()));
+		scores
+	}
+}
+
+impl Display for Scoreboard {
+	fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+		writeln!(formatter, "Rounds: {}", self.rounds.len())?;
+		writeln!(formatter, "Behaviors:")?;
+		for (rank, score) in self.behavior_ranking().iter().enumerate() {
+			writeln!(
+				formatter,
+				"{}. {}: Average score: {:.2}, Wins: {}, Agents: {}",
+				rank + 1,
+				score.behavior,
+				score.average_score(),
+				score.wins,
+				score.agent_count,
+			)?;
+		}
+		writeln!(formatter, "Agents:")?;
+		for (rank, score) in self.agent_ranking().iter().enumerate() {
+			writeln!(
+				formatter,
+				"{}. {} ({}): Score: {:.2}, Wins: {}",
+				rank + 1,
+				score.id,
+				score.behavior,
+				score.score,
+				score.wins,
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Accumulated score of a single agent
+#[derive(Clone)]
+pub struct AgentScore {
+	pub id: Id,
+	pub behavior: &'static str,
+	pub score: f32,
+	pub wins: usize,
+}
+
+/// Accumulated score of all agents with the same behavior
+#[derive(Clone)]
+pub struct BehaviorScore {
+	pub behavior: &'static str,
+	pub agent_count: usize,
+	pub score: f32,
+	pub wins: usize,
+}
+
+impl BehaviorScore {
+	/// Average score per agent, this makes populations of different sizes comparable
+	pub fn average_score(&self) -> f32 {
+		self.score / (self.agent_count as f32)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::behavior::chasing::ChasingBehavior;
+	use crate::behavior::default::DefaultBehavior;
+	use crate::behavior::BoxedBehavior;
+	use crate::game::ClassicTag;
+	use crate::termination::IterationLimit;
+	use crate::types::Vector;
+	use rand::rngs::SmallRng;
+	use rand::SeedableRng;
+
+	#[test]
+	fn should_accumulate_scores_over_multiple_rounds() {
+		let mut random_generator = SmallRng::seed_from_u64(42);
+		let behaviors = vec![
+			Box::new(DefaultBehavior) as BoxedBehavior,
+			Box::new(DefaultBehavior),
+			Box::new(ChasingBehavior::default()),
+		];
+		let mut world =
+			World::random_with_behaviors(Vector::new(100.0, 100.0), behaviors, false, &mut random_generator);
+		let runner = MatchRunner::new(Arc::new(ClassicTag::default()), 3, RoundStart::Randomize);
+
+		let scoreboard = runner.run(&mut world, || Box::new(IterationLimit(100)), &mut random_generator);
+
+		assert_eq!(3, scoreboard.rounds().len());
+		let total_iterations_as_it = scoreboard.agent_ranking().iter().map(|score| score.score).sum::<f32>();
+		assert_eq!(-300.0, total_iterations_as_it);
+
+		let behavior_ranking = scoreboard.behavior_ranking();
+		assert_eq!(2, behavior_ranking.len());
+		let default_score = behavior_ranking
+			.iter()
+			.find(|score| score.behavior == "default")
+			.unwrap();
+		assert_eq!(2, default_score.agent_count);
+	}
+
+	#[test]
+	fn should_reset_to_initial_positions() {
+		let mut random_generator = SmallRng::seed_from_u64(42);
+		let mut world = World::random(
+			Vector::new(100.0, 100.0),
+			5,
+			DefaultBehavior::default,
+			false,
+			&mut random_generator,
+		);
+		let initial_positions = world.agents().iter().map(|agent| agent.position).collect::<Vec<_>>();
+		let runner = MatchRunner::new(Arc::new(ClassicTag::default()), 2, RoundStart::Reset);
+
+		runner.run(&mut world, || Box::new(IterationLimit(0)), &mut random_generator);
+
+		let positions = world.agents().iter().map(|agent| agent.position).collect::<Vec<_>>();
+		assert_eq!(initial_positions, positions);
+	}
+
+	#[test]
+	fn should_parse_round_starts() {
+		assert_eq!(Ok(RoundStart::Reset), "reset".parse());
+		assert_eq!(Ok(RoundStart::Randomize), "randomize".parse());
+		assert!("restart".parse::<RoundStart>().is_err());
+	}
+}
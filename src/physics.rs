@@ -0,0 +1,42 @@
+use crate::agent::{Agent, AgentRelationShip};
+use crate::types::Radians;
+
+/// Physical parameters of a [`World`](crate::world::World) that apply to all agents.
+///
+/// The [`Default`] is given by the constants on [`Agent`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Physics {
+	/// How wide an agent can see
+	pub field_of_view_angle: Radians,
+	/// How far an agent is allowed to move in one time step.
+	pub maximum_velocity: f32,
+	/// How far an agent can reach
+	pub range: f32,
+	/// How far an agent can see. A limited view distance allows simulating the world in shards, see
+	/// [`ShardGrid`](crate::shard::ShardGrid).
+	pub view_distance: f32,
+}
+
+impl Default for Physics {
+	fn default() -> Self {
+		Self {
+			field_of_view_angle: Agent::FIELD_OF_VIEW_ANGLE,
+			maximum_velocity: Agent::MAXIMUM_VELOCITY,
+			range: Agent::RANGE,
+			view_distance: Agent::VIEW_DISTANCE,
+		}
+	}
+}
+
+impl Physics {
+	/// Can the other agent of the relationship be reached?
+	pub fn is_reachable(&self, relationship: &AgentRelationShip) -> bool {
+		relationship.distance <= self.range
+	}
+
+	/// Can the other agent of the relationship be seen?
+	pub fn is_visible(&self, relationship: &AgentRelationShip) -> bool {
+		(relationship.direction.abs() <= (self.field_of_view_angle / 2.0))
+			&& (relationship.distance <= self.view_distance)
+	}
+}
@@ -0,0 +1,489 @@
+use crate::game::GameMode;
+use crate::match_runner::{MatchRunner, RoundStart, Scoreboard};
+use crate::termination::{TerminationCondition, TerminationReason};
+use crate::viewer::Viewer;
+use crate::world::World;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+type TerminationConstructor = Box<dyn FnMut() -> Box<dyn TerminationCondition> + Send>;
+
+/// Runs the simulation loop: Owns a [`World`] and its [`Viewer`], steps the world at a target rate until
+/// the termination condition is met and notifies the viewer about every step.
+///
+/// While running, it can be paused, resumed, single-stepped and stopped from any thread through its [`RunnerControl`].
+pub struct SimulationRunner {
+	world: World,
+	game_mode: Arc<dyn GameMode>,
+	termination_constructor: TerminationConstructor,
+	viewer: Option<Arc<dyn Viewer>>,
+	rounds: usize,
+	round_start: RoundStart,
+	random_generator: SmallRng,
+	control: RunnerControl,
+}
+
+impl SimulationRunner {
+	/// Create a runner that plays a single round as fast as possible.
+	/// The termination condition for every round is created by the given constructor.
+	pub fn new(
+		world: World,
+		game_mode: Arc<dyn GameMode>,
+		termination_constructor: impl FnMut() -> Box<dyn TerminationCondition> + Send + 'static,
+	) -> Self {
+		Self {
+			world,
+			game_mode,
+			termination_constructor: Box::new(termination_constructor),
+			viewer: None,
+			rounds: 1,
+			round_start: RoundStart::default(),
+			random_generator: SmallRng::from_entropy(),
+			control: RunnerControl::new(0.0),
+		}
+	}
+
+	/// Notify the given viewer about every simulation step
+	pub fn with_viewer(mut self, viewer: Arc<dyn Viewer>) -> Self {
+		self.viewer = Some(viewer);
+		self
+	}
+
+	/// Play the given number of rounds, the agents are placed using the random generator at the start of every round
+	pub fn with_rounds(mut self, rounds: usize, random_generator: SmallRng) -> Self {
+		self.rounds = rounds;
+		self.random_generator = random_generator;
+		self
+	}
+
+	/// How the agents are placed at the start of every round after the first one, the default is
+	/// [`RoundStart::Randomize`]
+	pub fn with_round_start(mut self, round_start: RoundStart) -> Self {
+		self.round_start = round_start;
+		self
+	}
+
+	/// How many simulation steps to run per second, zero means as fast as possible
+	pub fn with_ticks_per_second(self, ticks_per_second: f64) -> Self {
+		self.control.set_ticks_per_second(ticks_per_second);
+		self
+	}
+
+	/// Start in the paused state, so the simulation only runs once resumed or stepped
+	pub fn paused(self) -> Self {
+		self.control.pause();
+		self
+	}
+
+	/// Handle to control the runner from other threads
+	pub fn control(&self) -> RunnerControl {
+		self.control.clone()
+	}
+
+	pub fn world(&self) -> &World {
+		&self.world
+	}
+
+	/// Run all rounds, blocking the current thread until done or stopped.
+	pub fn run(mut self) -> Scoreboard {
+		let match_runner = MatchRunner::new(self.game_mode.clone(), self.rounds, self.round_start);
+		let scoreboard = match_runner.run_rounds(&mut self.world, &mut self.random_generator, |world| {
+			Self::run_round(
+				world,
+				self.termination_constructor.as_mut(),
+				&self.control,
+				self.viewer.as_deref(),
+			)
+		});
+
+		if let Some(viewer) = &self.viewer {
+			viewer.finished(&self.world);
+		}
+		scoreboard
+	}
+
+	fn run_round(
+		world: &mut World,
+		termination_constructor: &mut (dyn FnMut() -> Box<dyn TerminationCondition> + Send),
+		control: &RunnerControl,
+		viewer: Option<&dyn Viewer>,
+	) -> TerminationReason {
+		if let Some(viewer) = viewer {
+			viewer.round_started(world);
+		}
+
+		let mut termination = termination_constructor();
+		let mut pacer = Pacer::default();
+		let reason = loop {
+			if let Some(reason) = termination.check(world) {
+				break reason;
+			}
+
+			if let Tick::Stop = control.wait_for_tick(&mut pacer) {
+				break TerminationReason::Stopped;
+			}
+
+			world.simulate_step();
+			if let Some(viewer) = viewer {
+				viewer.iteration(world);
+			}
+		};
+
+		if let Some(viewer) = viewer {
+			viewer.round_finished(world);
+		}
+		reason
+	}
+}
+
+/// Handle to pause, resume, step and stop a [`SimulationRunner`] from other threads.
+#[derive(Clone)]
+pub struct RunnerControl {
+	shared: Arc<(Mutex<ControlState>, Condvar)>,
+}
+
+struct ControlState {
+	paused: bool,
+	pending_steps: usize,
+	stopped: bool,
+	ticks_per_second: f64,
+}
+
+enum Tick {
+	/// Run the next step at the target rate
+	Run,
+	/// Run a single step while paused
+	Step,
+	Stop,
+}
+
+impl RunnerControl {
+	/// Slowest rate other than as fast as possible, slower rates are raised to it.
+	/// Much slower rates would wait longer than a [`Duration`] can hold.
+	pub const MINIMUM_TICKS_PER_SECOND: f64 = 0.001;
+
+	fn new(ticks_per_second: f64) -> Self {
+		let state = ControlState {
+			paused: false,
+			pending_steps: 0,
+			stopped: false,
+			ticks_per_second,
+		};
+		Self {
+			shared: Arc::new((Mutex::new(state), Condvar::new())),
+		}
+	}
+
+	fn update(&self, update: impl FnOnce(&mut ControlState)) {
+		let (state, condition) = &*self.shared;
+		update(&mut state.lock().expect("Lock was poisoned"));
+		condition.notify_all();
+	}
+
+	fn read<Value>(&self, read: impl FnOnce(&ControlState) -> Value) -> Value {
+		read(&self.shared.0.lock().expect("Lock was poisoned"))
+	}
+
+	pub fn pause(&self) {
+		self.update(|state| state.paused = true);
+	}
+
+	pub fn resume(&self) {
+		self.update(|state| {
+			state.paused = false;
+			state.pending_steps = 0;
+		});
+	}
+
+	/// Pause if running, resume if paused
+	pub fn toggle_pause(&self) {
+		if self.is_paused() {
+			self.resume();
+		} else {
+			self.pause();
+		}
+	}
+
+	/// Run exactly one step. This only has an effect while paused.
+	pub fn step(&self) {
+		self.update(|state| {
+			if state.paused {
+				state.pending_steps += 1;
+			}
+		});
+	}
+
+	/// Stop the simulation, this ends all remaining rounds.
+	pub fn stop(&self) {
+		self.update(|state| state.stopped = true);
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.read(|state| state.paused)
+	}
+
+	pub fn is_stopped(&self) -> bool {
+		self.read(|state| state.stopped)
+	}
+
+	pub fn ticks_per_second(&self) -> f64 {
+		self.read(|state| state.ticks_per_second)
+	}
+
+	/// How many simulation steps to run per second, zero means as fast as possible
+	pub fn set_ticks_per_second(&self, ticks_per_second: f64) {
+		self.update(|state| {
+			state.ticks_per_second = if ticks_per_second > 0.0 {
+				ticks_per_second.max(Self::MINIMUM_TICKS_PER_SECOND)
+			} else {
+				0.0
+			};
+		});
+	}
+
+	/// Double the speed, unless it's already as fast as possible
+	pub fn speed_up(&self) {
+		self.update(|state| {
+			if state.ticks_per_second > 0.0 {
+				state.ticks_per_second *= 2.0;
+			}
+		});
+	}
+
+	/// Halve the speed down to [`RunnerControl::MINIMUM_TICKS_PER_SECOND`]. When running as fast as possible, slow
+	/// down to a high rate.
+	pub fn slow_down(&self) {
+		self.update(|state| {
+			state.ticks_per_second = if state.ticks_per_second <= 0.0 {
+				1000.0
+			} else {
+				(state.ticks_per_second / 2.0).max(Self::MINIMUM_TICKS_PER_SECOND)
+			};
+		});
+	}
+
+	/// Blocks while paused or until the next step is due at the target rate. Waiting is interrupted by every change
+	/// of the control, so stopping, pausing or changing the speed takes effect right away.
+	fn wait_for_tick(&self, pacer: &mut Pacer) -> Tick {
+		let (state, condition) = &*self.shared;
+		let mut state = state.lock().expect("Lock was poisoned");
+		let mut waited = false;
+		loop {
+			if state.stopped {
+				return Tick::Stop;
+			}
+			if state.paused {
+				if state.pending_steps > 0 {
+					state.pending_steps -= 1;
+					pacer.reset();
+					return Tick::Step;
+				}
+				state = condition.wait(state).expect("Lock was poisoned");
+				continue;
+			}
+
+			let now = Instant::now();
+			match pacer.remaining(state.ticks_per_second, now) {
+				Some(remaining) => {
+					state = condition.wait_timeout(state, remaining).expect("Lock was poisoned").0;
+					waited = true;
+				}
+				None => {
+					pacer.tick(state.ticks_per_second, now, waited);
+					return Tick::Run;
+				}
+			}
+		}
+	}
+}
+
+/// Keeps the simulation at a target rate by waiting until the next tick is due.
+/// Unlike a fixed delay, this accounts for the time the simulation step itself takes.
+#[derive(Default)]
+struct Pacer {
+	last_tick: Option<Instant>,
+}
+
+impl Pacer {
+	/// How long until the next tick is due at the given rate, `None` if it is due already
+	fn remaining(&self, ticks_per_second: f64, now: Instant) -> Option<Duration> {
+		if ticks_per_second <= 0.0 {
+			return None;
+		}
+
+		let next_tick = self.last_tick? + Duration::from_secs_f64(1.0 / ticks_per_second);
+		(next_tick > now).then(|| next_tick - now)
+	}
+
+	/// A tick happens now. If it was waited for, it happens exactly when it was due. Otherwise the simulation fell
+	/// behind and doesn't try to catch up by running a burst of steps.
+	fn tick(&mut self, ticks_per_second: f64, now: Instant, waited: bool) {
+		self.last_tick = match self.last_tick {
+			_ if ticks_per_second <= 0.0 => None,
+			Some(last_tick) if waited => Some(last_tick + Duration::from_secs_f64(1.0 / ticks_per_second)),
+			_ => Some(now),
+		};
+	}
+
+	fn reset(&mut self) {
+		self.last_tick = None;
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::behavior::default::DefaultBehavior;
+	use crate::game::ClassicTag;
+	use crate::termination::IterationLimit;
+	use crate::types::Vector;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	fn runner(iterations: usize) -> SimulationRunner {
+		let world = World::random(
+			Vector::new(100.0, 100.0),
+			5,
+			DefaultBehavior::default,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		);
+		SimulationRunner::new(world, Arc::new(ClassicTag::default()), move || {
+			Box::new(IterationLimit(iterations))
+		})
+	}
+
+	#[derive(Default)]
+	struct CountingViewer {
+		iterations: AtomicUsize,
+		finished: AtomicUsize,
+	}
+
+	impl Viewer for CountingViewer {
+		fn iteration(&self, _world: &World) {
+			self.iterations.fetch_add(1, Ordering::SeqCst);
+		}
+
+		fn finished(&self, _world: &World) {
+			self.finished.fetch_add(1, Ordering::SeqCst);
+		}
+
+		fn run(&self) {}
+	}
+
+	#[test]
+	fn should_run_all_rounds() {
+		let viewer = Arc::new(CountingViewer::default());
+		let runner = runner(10)
+			.with_viewer(viewer.clone())
+			.with_rounds(3, SmallRng::seed_from_u64(1));
+
+		let scoreboard = runner.run();
+
+		assert_eq!(3, scoreboard.rounds().len());
+		assert_eq!(30, viewer.iterations.load(Ordering::SeqCst));
+		assert_eq!(1, viewer.finished.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn should_stop_immediately() {
+		let runner = runner(10).with_rounds(3, SmallRng::seed_from_u64(1));
+		runner.control().stop();
+
+		let scoreboard = runner.run();
+
+		assert_eq!(1, scoreboard.rounds().len());
+		assert_eq!(TerminationReason::Stopped, scoreboard.rounds()[0].reason);
+		assert_eq!(0, scoreboard.rounds()[0].iterations);
+	}
+
+	#[test]
+	fn should_single_step_while_paused() {
+		let viewer = Arc::new(CountingViewer::default());
+		let runner = runner(1000).with_viewer(viewer.clone()).paused();
+		let control = runner.control();
+		let handle = std::thread::spawn(move || runner.run());
+
+		for _ in 0..3 {
+			control.step();
+		}
+		let start = Instant::now();
+		while viewer.iterations.load(Ordering::SeqCst) < 3 {
+			assert!(start.elapsed() < Duration::from_secs(10), "Steps were not performed");
+			std::thread::yield_now();
+		}
+		control.stop();
+
+		let scoreboard = handle.join().unwrap();
+		assert_eq!(3, scoreboard.rounds()[0].iterations);
+	}
+
+	#[test]
+	fn should_change_speed() {
+		let control = runner(1).with_ticks_per_second(20.0).control();
+
+		control.speed_up();
+		assert_eq!(40.0, control.ticks_per_second());
+		control.slow_down();
+		control.slow_down();
+		assert_eq!(10.0, control.ticks_per_second());
+
+		control.set_ticks_per_second(0.0);
+		control.speed_up();
+		assert_eq!(0.0, control.ticks_per_second());
+		control.slow_down();
+		assert_eq!(1000.0, control.ticks_per_second());
+
+		control.set_ticks_per_second(1e-300);
+		assert_eq!(RunnerControl::MINIMUM_TICKS_PER_SECOND, control.ticks_per_second());
+
+		// slowing down never speeds up
+		control.set_ticks_per_second(0.5);
+		control.slow_down();
+		assert_eq!(0.25, control.ticks_per_second());
+		control.set_ticks_per_second(RunnerControl::MINIMUM_TICKS_PER_SECOND);
+		control.slow_down();
+		assert_eq!(RunnerControl::MINIMUM_TICKS_PER_SECOND, control.ticks_per_second());
+	}
+
+	#[test]
+	fn should_stop_and_speed_up_while_waiting_for_a_slow_tick() {
+		let viewer = Arc::new(CountingViewer::default());
+		let runner = runner(1000)
+			.with_viewer(viewer.clone())
+			.with_ticks_per_second(RunnerControl::MINIMUM_TICKS_PER_SECOND);
+		let control = runner.control();
+		let handle = std::thread::spawn(move || runner.run());
+
+		let start = Instant::now();
+		let wait_for_iterations = |iterations| {
+			while viewer.iterations.load(Ordering::SeqCst) < iterations {
+				assert!(start.elapsed() < Duration::from_secs(10), "Waited for the slow tick");
+				std::thread::yield_now();
+			}
+		};
+		// the first tick happens immediately, the next one would be 1000s later
+		wait_for_iterations(1);
+		control.set_ticks_per_second(1000.0);
+		wait_for_iterations(2);
+		control.set_ticks_per_second(RunnerControl::MINIMUM_TICKS_PER_SECOND);
+		std::thread::sleep(Duration::from_millis(10));
+		control.stop();
+
+		let scoreboard = handle.join().unwrap();
+		assert!(start.elapsed() < Duration::from_secs(10));
+		assert_eq!(TerminationReason::Stopped, scoreboard.rounds()[0].reason);
+	}
+
+	#[test]
+	fn should_pace_to_ticks_per_second() {
+		let runner = runner(5).with_ticks_per_second(100.0);
+
+		let start = Instant::now();
+		runner.run();
+
+		// the first tick happens immediately, the other 4 are 10ms apart
+		assert!(start.elapsed() >= Duration::from_millis(40));
+	}
+}
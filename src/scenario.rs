@@ -0,0 +1,613 @@
+use crate::behavior::registry::BehaviorRegistry;
+use crate::execution::ExecutionStrategy;
+use crate::game::{ClassicTag, GameMode};
+use crate::image_export::ImageFormat;
+use crate::layout::AgentLayout;
+use crate::match_runner::RoundStart;
+use crate::physics::Physics;
+use crate::runner::{RunnerControl, SimulationRunner};
+use crate::termination::{IterationLimit, Stalemate, TerminationCondition, TimeLimit, Victory};
+use crate::types::{degrees_to_radians, radians_to_degrees, Vector};
+use crate::world::World;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Full configuration of a simulation run, usually loaded from a TOML file.
+///
+/// Every field has a default, so a scenario file only needs to contain what differs from it:
+/// ```toml
+/// [world]
+/// width = 800
+///
+/// [[agents]]
+/// behavior = "chasing"
+/// count = 5
+///
+/// [[agents]]
+/// behavior = "runaway"
+/// count = 5
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+	pub world: WorldConfiguration,
+	pub agents: Vec<AgentGroup>,
+	pub rules: Rules,
+	pub viewer: ViewerConfiguration,
+	pub output: OutputConfiguration,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorldConfiguration {
+	/// Width of the playing field
+	pub width: f32,
+	/// Height of the playing field
+	pub height: f32,
+	/// Run the simulation in parallel using rayon
+	pub parallel: bool,
+	/// How the behaviors are run, overrides `parallel`, see [`ExecutionStrategy`]. Can't be combined with `shards`.
+	pub execution: Option<ExecutionStrategy>,
+	/// How the agents are stored, see [`AgentLayout`]
+	pub layout: AgentLayout,
+	/// Simulate the world in about this many regions in parallel, see [`ShardGrid`](crate::shard::ShardGrid)
+	pub shards: Option<usize>,
+	/// Seed for placing the agents, random if not given
+	pub seed: Option<u64>,
+}
+
+impl Default for WorldConfiguration {
+	fn default() -> Self {
+		Self {
+			width: 500.0,
+			height: 500.0,
+			parallel: false,
+			execution: None,
+			layout: AgentLayout::default(),
+			shards: None,
+			seed: None,
+		}
+	}
+}
+
+/// A number of agents that share the same behavior
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AgentGroup {
+	/// Name of the behavior in the [`BehaviorRegistry`]
+	pub behavior: String,
+	pub count: usize,
+}
+
+impl Default for AgentGroup {
+	fn default() -> Self {
+		Self {
+			behavior: "default".to_string(),
+			count: 10,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+	/// How many iterations to simulate at most per round
+	pub iterations: usize,
+	/// Stop a round after this many seconds of wall-clock time
+	pub time_limit_seconds: Option<f64>,
+	/// A round is decided after this many tags
+	pub tag_limit: Option<usize>,
+	/// Stop a round if "it" hasn't seen anybody for this many iterations
+	pub stalemate_iterations: Option<usize>,
+	/// Number of rounds to play
+	pub rounds: usize,
+	/// How the agents are placed at the start of every round after the first one
+	pub round_start: RoundStart,
+	pub physics: PhysicsConfiguration,
+}
+
+impl Default for Rules {
+	fn default() -> Self {
+		Self {
+			iterations: 10000,
+			time_limit_seconds: None,
+			tag_limit: None,
+			stalemate_iterations: None,
+			rounds: 1,
+			round_start: RoundStart::default(),
+			physics: Default::default(),
+		}
+	}
+}
+
+impl Rules {
+	/// Create the termination conditions for one round
+	pub fn termination_conditions(&self, game_mode: Arc<dyn GameMode>) -> Vec<Box<dyn TerminationCondition>> {
+		let mut conditions: Vec<Box<dyn TerminationCondition>> =
+			vec![Box::new(IterationLimit(self.iterations)), Box::new(Victory(game_mode))];
+		if let Some(seconds) = self.time_limit_seconds {
+			conditions.push(Box::new(TimeLimit::new(Duration::from_secs_f64(seconds))));
+		}
+		if let Some(iterations) = self.stalemate_iterations {
+			conditions.push(Box::new(Stalemate(iterations)));
+		}
+
+		conditions
+	}
+}
+
+/// The same as [`Physics`], but with the field of view in degrees
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PhysicsConfiguration {
+	pub field_of_view_degrees: f32,
+	pub maximum_velocity: f32,
+	pub range: f32,
+	/// Unlimited by default, which TOML writes as `inf`
+	pub view_distance: f32,
+}
+
+impl Default for PhysicsConfiguration {
+	fn default() -> Self {
+		let physics = Physics::default();
+		Self {
+			field_of_view_degrees: radians_to_degrees(physics.field_of_view_angle),
+			maximum_velocity: physics.maximum_velocity,
+			range: physics.range,
+			view_distance: physics.view_distance,
+		}
+	}
+}
+
+impl From<&PhysicsConfiguration> for Physics {
+	fn from(configuration: &PhysicsConfiguration) -> Self {
+		Self {
+			field_of_view_angle: degrees_to_radians(configuration.field_of_view_degrees),
+			maximum_velocity: configuration.maximum_velocity,
+			range: configuration.range,
+			view_distance: configuration.view_distance,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewerConfiguration {
+	/// How should the simulation be displayed, multiple viewers can be used at once.
+	/// Older scenarios give a single `kind` instead.
+	#[serde(alias = "kind", deserialize_with = "one_or_many")]
+	pub kinds: Vec<ViewerKind>,
+	/// How many iterations to simulate per second, zero means as fast as possible
+	pub ticks_per_second: f64,
+}
+
+impl Default for ViewerConfiguration {
+	fn default() -> Self {
+		Self {
+			kinds: vec![ViewerKind::Visual],
+			ticks_per_second: 20.0,
+		}
+	}
+}
+
+/// Deserialize either a single value or a list of values
+fn one_or_many<'de, D, Value>(deserializer: D) -> Result<Vec<Value>, D::Error>
+where
+	D: Deserializer<'de>,
+	Value: Deserialize<'de>,
+{
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum OneOrMany<Value> {
+		One(Value),
+		Many(Vec<Value>),
+	}
+
+	Ok(match OneOrMany::deserialize(deserializer)? {
+		OneOrMany::One(value) => vec![value],
+		OneOrMany::Many(values) => values,
+	})
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ViewerKind {
+	Visual,
+	CommandLine,
+	Terminal,
+	JsonLines,
+	Images,
+	Svg,
+}
+
+impl FromStr for ViewerKind {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		use ViewerKind::*;
+		match text {
+			"visual" => Ok(Visual),
+			"command-line" => Ok(CommandLine),
+			"terminal" => Ok(Terminal),
+			"json-lines" => Ok(JsonLines),
+			"images" => Ok(Images),
+			"svg" => Ok(Svg),
+			_ => Err(format!("Invalid viewer option: {text}")),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfiguration {
+	/// File to write the results of all rounds to as JSON
+	pub results: Option<PathBuf>,
+	/// File the `json-lines` viewer writes to, stdout if not given
+	pub json_lines: Option<PathBuf>,
+	/// The `json-lines` viewer only records every n-th iteration
+	pub json_lines_interval: usize,
+	/// File or directory (for `png`) the `images` viewer writes to
+	pub images: Option<PathBuf>,
+	/// Format of the `images` viewer (png, gif or apng)
+	pub image_format: ImageFormat,
+	/// The `images` viewer only renders every n-th iteration
+	pub image_interval: usize,
+	/// Pixels per unit of the playing field
+	pub image_scale: f32,
+	/// File the `svg` viewer writes the final iteration to
+	pub svg: Option<PathBuf>,
+	/// The `svg` viewer draws trails and tags of this many iterations
+	pub svg_window: usize,
+}
+
+impl Default for OutputConfiguration {
+	fn default() -> Self {
+		Self {
+			results: None,
+			json_lines: None,
+			json_lines_interval: 1,
+			images: None,
+			image_format: ImageFormat::Gif,
+			image_interval: 1,
+			image_scale: 1.0,
+			svg: None,
+			svg_window: 50,
+		}
+	}
+}
+
+/// A [`Scenario`] that was validated and turned into everything needed to run it.
+pub struct Simulation {
+	pub world: World,
+	pub game_mode: Arc<dyn GameMode>,
+	pub rules: Rules,
+	pub viewer: ViewerConfiguration,
+	pub output: OutputConfiguration,
+	pub random_generator: SmallRng,
+}
+
+impl Simulation {
+	/// Create the termination conditions for one round
+	pub fn termination_conditions(&self) -> Vec<Box<dyn TerminationCondition>> {
+		self.rules.termination_conditions(self.game_mode.clone())
+	}
+
+	/// Turn this into a [`SimulationRunner`] that follows the rules and is paced as configured for the viewer
+	pub fn into_runner(self) -> SimulationRunner {
+		let rules = self.rules;
+		let (rounds, round_start) = (rules.rounds, rules.round_start);
+		let game_mode = self.game_mode.clone();
+		SimulationRunner::new(self.world, self.game_mode, move || {
+			Box::new(rules.termination_conditions(game_mode.clone()))
+		})
+		.with_rounds(rounds, self.random_generator)
+		.with_round_start(round_start)
+		.with_ticks_per_second(self.viewer.ticks_per_second)
+	}
+}
+
+impl Scenario {
+	/// Load a scenario from a TOML file
+	pub fn load(path: &Path) -> Result<Self, String> {
+		let text =
+			std::fs::read_to_string(path).map_err(|error| format!("Failed to read {}: {error}", path.display()))?;
+		text.parse()
+	}
+
+	/// Size of the playing field
+	pub fn bounds(&self) -> Vector {
+		Vector::new(self.world.width, self.world.height)
+	}
+
+	/// Total number of agents over all groups
+	pub fn agent_count(&self) -> usize {
+		self.agents.iter().map(|group| group.count).sum()
+	}
+
+	/// Groups of agents, a scenario without any groups gets the default group
+	pub fn agent_groups(&self) -> Vec<AgentGroup> {
+		if self.agents.is_empty() {
+			vec![AgentGroup::default()]
+		} else {
+			self.agents.clone()
+		}
+	}
+
+	/// Change the total number of agents, keeping the proportions between the groups as far as possible
+	pub fn scale_agent_groups(&mut self, agent_count: usize) {
+		let mut groups = self.agent_groups();
+		let total = groups.iter().map(|group| group.count).sum::<usize>();
+		if total == 0 {
+			groups.truncate(1);
+			groups[0].count = agent_count;
+			self.agents = groups;
+			return;
+		}
+
+		// every group gets its share rounded down, the agents that are left go to the largest remainders
+		let shares = groups.iter().map(|group| group.count * agent_count).collect::<Vec<_>>();
+		for (group, share) in groups.iter_mut().zip(&shares) {
+			group.count = share / total;
+		}
+		let mut by_remainder = (0..groups.len()).collect::<Vec<_>>();
+		by_remainder.sort_by_key(|&index| std::cmp::Reverse(shares[index] % total));
+		let left = agent_count - groups.iter().map(|group| group.count).sum::<usize>();
+		for &index in by_remainder.iter().take(left) {
+			groups[index].count += 1;
+		}
+		self.agents = groups;
+	}
+
+	/// Check that the scenario makes sense, with behaviors being looked up in the given registry
+	pub fn validate(&self, registry: &BehaviorRegistry) -> Result<(), String> {
+		let world = &self.world;
+		if !(world.width > 0.0 && world.height > 0.0 && world.width.is_finite() && world.height.is_finite()) {
+			return Err(format!(
+				"The playing field must be positive and finite, got {}x{}",
+				world.width, world.height
+			));
+		}
+
+		if world.execution == Some(ExecutionStrategy::Chunked(0)) {
+			return Err("The chunk size must be positive".to_string());
+		}
+		if world.shards == Some(0) {
+			return Err("There must be at least one shard".to_string());
+		}
+		if let (Some(execution), Some(_)) = (world.execution, world.shards) {
+			return Err(format!(
+				"Shards are always simulated in parallel, they can't be combined with the execution strategy {execution:?}"
+			));
+		}
+
+		let agent_groups = self.agent_groups();
+		if agent_groups.iter().map(|group| group.count).sum::<usize>() == 0 {
+			return Err("There must be at least one agent".to_string());
+		}
+		for group in &agent_groups {
+			registry.create(&group.behavior)?;
+		}
+
+		let rules = &self.rules;
+		if rules.rounds == 0 {
+			return Err("There must be at least one round".to_string());
+		}
+		if self.viewer.kinds.is_empty() {
+			return Err("There must be at least one viewer".to_string());
+		}
+		let ticks_per_second = self.viewer.ticks_per_second;
+		if ticks_per_second.is_nan() || (ticks_per_second < 0.0) {
+			return Err(format!("Invalid ticks per second: {ticks_per_second}"));
+		}
+		if (ticks_per_second > 0.0) && (ticks_per_second < RunnerControl::MINIMUM_TICKS_PER_SECOND) {
+			return Err(format!(
+				"Ticks per second must be 0 or at least {}, got {ticks_per_second:?}",
+				RunnerControl::MINIMUM_TICKS_PER_SECOND
+			));
+		}
+		let output = &self.output;
+		if output.json_lines_interval == 0 {
+			return Err("The JSON lines interval must be positive".to_string());
+		}
+		if output.image_interval == 0 {
+			return Err("The image interval must be positive".to_string());
+		}
+		if !(output.image_scale > 0.0 && output.image_scale.is_finite()) {
+			return Err(format!("Invalid image scale: {}", output.image_scale));
+		}
+		if self.viewer.kinds.contains(&ViewerKind::Images) && output.images.is_none() {
+			return Err("The images viewer needs a path to write the images to".to_string());
+		}
+		if self.viewer.kinds.contains(&ViewerKind::Svg) && output.svg.is_none() {
+			return Err("The svg viewer needs a path to write the SVG to".to_string());
+		}
+		if let Some(seconds) = rules.time_limit_seconds {
+			if !(seconds >= 0.0 && seconds.is_finite()) {
+				return Err(format!("Invalid time limit: {seconds}"));
+			}
+		}
+
+		let physics = &rules.physics;
+		if !(0.0..=360.0).contains(&physics.field_of_view_degrees) {
+			return Err(format!(
+				"The field of view must be between 0 and 360 degrees, got {}",
+				physics.field_of_view_degrees
+			));
+		}
+		if !(physics.maximum_velocity >= 0.0 && physics.range >= 0.0) {
+			return Err("Maximum velocity and range must not be negative".to_string());
+		}
+		if physics.view_distance.is_nan() || physics.view_distance < 0.0 {
+			return Err(format!(
+				"The view distance must not be negative, got {}",
+				physics.view_distance
+			));
+		}
+		if world.shards.is_some() && !physics.view_distance.is_finite() {
+			return Err(
+				"Shards need a limited view distance, every agent could see the whole world otherwise".to_string(),
+			);
+		}
+
+		Ok(())
+	}
+
+	/// Validate the scenario and construct the [`World`] and everything else needed to run it
+	pub fn build(&self, registry: &BehaviorRegistry) -> Result<Simulation, String> {
+		self.validate(registry)?;
+
+		let mut random_generator = match self.world.seed {
+			Some(seed) => SmallRng::seed_from_u64(seed),
+			None => SmallRng::from_entropy(),
+		};
+		let behaviors = self
+			.agent_groups()
+			.iter()
+			.flat_map(|group| std::iter::repeat_n(&group.behavior, group.count))
+			.map(|behavior| registry.create(behavior))
+			.collect::<Result<Vec<_>, _>>()?;
+		let mut world =
+			World::random_with_behaviors(self.bounds(), behaviors, self.world.parallel, &mut random_generator)
+				.with_physics(Physics::from(&self.rules.physics))
+				.with_layout(self.world.layout);
+		if let Some(execution) = self.world.execution {
+			world = world.with_execution_strategy(execution);
+		}
+		if let Some(shards) = self.world.shards {
+			world = world.with_shards(shards);
+		}
+
+		Ok(Simulation {
+			world,
+			game_mode: Arc::new(ClassicTag {
+				tag_limit: self.rules.tag_limit,
+			}),
+			rules: self.rules.clone(),
+			viewer: self.viewer.clone(),
+			output: self.output.clone(),
+			random_generator,
+		})
+	}
+}
+
+impl FromStr for Scenario {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		toml::from_str(text).map_err(|error| format!("Invalid scenario: {error}"))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_parse_example_scenario() {
+		let scenario = include_str!("../scenarios/example.toml").parse::<Scenario>().unwrap();
+
+		assert_eq!(2, scenario.agents.len());
+		assert_eq!(10, scenario.agent_count());
+		let simulation = scenario.build(&BehaviorRegistry::default()).unwrap();
+		assert_eq!(10, simulation.world.agent_count());
+		assert_eq!(Some(42), scenario.world.seed);
+		assert_eq!(AgentLayout::ArrayOfStructs, scenario.world.layout);
+	}
+
+	#[test]
+	fn should_use_defaults_for_missing_fields() {
+		let scenario = "[world]\nwidth = 800".parse::<Scenario>().unwrap();
+
+		assert_eq!(800.0, scenario.world.width);
+		assert_eq!(WorldConfiguration::default().height, scenario.world.height);
+		assert_eq!(Rules::default(), scenario.rules);
+		assert_eq!(vec![AgentGroup::default()], scenario.agent_groups());
+	}
+
+	#[test]
+	fn should_accept_a_single_viewer_kind() {
+		let scenario = "[viewer]\nkind = \"terminal\"".parse::<Scenario>().unwrap();
+		assert_eq!(vec![ViewerKind::Terminal], scenario.viewer.kinds);
+
+		let scenario = "[viewer]\nkinds = [\"svg\", \"json-lines\"]"
+			.parse::<Scenario>()
+			.unwrap();
+		assert_eq!(vec![ViewerKind::Svg, ViewerKind::JsonLines], scenario.viewer.kinds);
+	}
+
+	#[test]
+	fn should_scale_agent_groups() {
+		let mut scenario = include_str!("../scenarios/example.toml").parse::<Scenario>().unwrap();
+
+		scenario.scale_agent_groups(7);
+		let counts = scenario.agents.iter().map(|group| group.count).collect::<Vec<_>>();
+		assert_eq!(vec![4, 3], counts);
+		assert_eq!("runaway", scenario.agents[1].behavior);
+
+		scenario.scale_agent_groups(70);
+		assert_eq!(70, scenario.agent_count());
+	}
+
+	#[test]
+	fn should_reject_invalid_scenarios() {
+		let registry = BehaviorRegistry::default();
+
+		assert!("[world]\nwidht = 800".parse::<Scenario>().is_err());
+		assert!("[[agents]]\nbehavior = \"unknown\"\ncount = 1"
+			.parse::<Scenario>()
+			.unwrap()
+			.validate(&registry)
+			.is_err());
+		assert!("[world]\nwidth = inf"
+			.parse::<Scenario>()
+			.unwrap()
+			.validate(&registry)
+			.is_err());
+		assert!("[world]\nheight = 0"
+			.parse::<Scenario>()
+			.unwrap()
+			.validate(&registry)
+			.is_err());
+		assert!("[world]\nshards = 4"
+			.parse::<Scenario>()
+			.unwrap()
+			.validate(&registry)
+			.is_err());
+		assert!("[world]\nshards = 4\n[rules.physics]\nview_distance = 20"
+			.parse::<Scenario>()
+			.unwrap()
+			.validate(&registry)
+			.is_ok());
+		assert!("[world]\nshards = 0"
+			.parse::<Scenario>()
+			.unwrap()
+			.validate(&registry)
+			.is_err());
+		assert!("[world]\nexecution = \"chunked:0\"".parse::<Scenario>().is_err());
+		assert!("[world]\nexecution = \"chunked:64\"\nshards = 4"
+			.parse::<Scenario>()
+			.unwrap()
+			.validate(&registry)
+			.is_err());
+		assert!("[world]\nexecution = \"automatic\""
+			.parse::<Scenario>()
+			.unwrap()
+			.validate(&registry)
+			.is_ok());
+		assert!("[viewer]\nticks_per_second = 1e-300"
+			.parse::<Scenario>()
+			.unwrap()
+			.validate(&registry)
+			.is_err());
+		assert!("[rules]\nrounds = 0"
+			.parse::<Scenario>()
+			.unwrap()
+			.validate(&registry)
+			.is_err());
+	}
+}
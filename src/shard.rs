@@ -0,0 +1,194 @@
+use crate::agent::Agent;
+use crate::id::Id;
+use crate::types::Vector;
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use rayon::slice::ParallelSlice;
+
+/// Partition of the arena into a grid of rectangular regions ("shards") that are simulated by separate rayon tasks.
+///
+/// Agents only see others within [`Physics::view_distance`](crate::physics::Physics::view_distance), and only reach
+/// the ones they see, so every region only needs its own agents plus the ones of other regions that are at most the
+/// view distance away from its border (the "halo"). The view distance has to be limited for this to pay off.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShardGrid {
+	columns: usize,
+	rows: usize,
+	region_size: Vector,
+}
+
+/// Agents that are partitioned by one task
+const PARTITION_CHUNK_SIZE: usize = 16 * 1024;
+
+/// The agents that are simulated by one task
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Region {
+	/// Agents located in this region, sorted by [`Id`]
+	pub members: Vec<Id>,
+	/// The members together with the agents of other regions that are at most the view distance away, sorted by [`Id`]
+	pub neighborhood: Vec<Id>,
+}
+
+impl ShardGrid {
+	/// Divide the given arena into about `shard_count` regions that are as square as possible
+	pub fn new(bounds: Vector, shard_count: usize) -> Self {
+		let shard_count = shard_count.max(1);
+		let aspect_ratio = if bounds.y > 0.0 { bounds.x / bounds.y } else { 1.0 };
+		let columns = ((shard_count as f32 * aspect_ratio).sqrt().round() as usize).clamp(1, shard_count);
+		let rows = shard_count.div_ceil(columns);
+		Self {
+			columns,
+			rows,
+			region_size: Vector::new(bounds.x / columns as f32, bounds.y / rows as f32),
+		}
+	}
+
+	pub fn region_count(&self) -> usize {
+		self.columns * self.rows
+	}
+
+	/// Index of the region containing the given position, the same as the one of the [`Region`] it is a member of
+	pub(crate) fn region_index(&self, position: Vector) -> usize {
+		let (column, row) = self.cell(position);
+		row * self.columns + column
+	}
+
+	/// Column and row of the region containing the given position, positions on the edge belong to the last one
+	fn cell(&self, position: Vector) -> (usize, usize) {
+		let clamp = |value: f32, size: f32, count: usize| {
+			if size > 0.0 {
+				((value / size).max(0.0) as usize).min(count - 1)
+			} else {
+				0
+			}
+		};
+		(
+			clamp(position.x, self.region_size.x, self.columns),
+			clamp(position.y, self.region_size.y, self.rows),
+		)
+	}
+
+	/// Distance of the given position to the rectangle of the region in the given cell
+	fn distance_to_region(&self, position: Vector, (column, row): (usize, usize)) -> f32 {
+		let minimum = Vector::new(column as f32, row as f32) * self.region_size;
+		let maximum = minimum + self.region_size;
+		let nearest = position.max(minimum).min(maximum);
+		position.distance(nearest)
+	}
+
+	/// Assign every agent to its region and exchange the halos of the given width between the regions.
+	///
+	/// Chunks of agents are partitioned in parallel, concatenating the chunks in order keeps the [`Id`]s sorted.
+	pub(crate) fn partition(&self, agents: &[Agent], halo_width: f32) -> Vec<Region> {
+		let chunks = agents
+			.par_chunks(PARTITION_CHUNK_SIZE)
+			.enumerate()
+			.map(|(chunk_index, chunk)| self.partition_chunk(chunk_index * PARTITION_CHUNK_SIZE, chunk, halo_width))
+			.collect::<Vec<_>>();
+
+		(0..self.region_count())
+			.into_par_iter()
+			.map(|region_index| {
+				let members = chunks
+					.iter()
+					.flat_map(|(members, _)| &members[region_index])
+					.copied()
+					.collect::<Vec<_>>();
+				let halo = chunks
+					.iter()
+					.flat_map(|(_, halos)| &halos[region_index])
+					.copied()
+					.collect::<Vec<_>>();
+				Region {
+					neighborhood: merge_sorted(&members, &halo),
+					members,
+				}
+			})
+			.collect()
+	}
+
+	/// Members and halos of every region for the given agents, which start at the given [`Id`]
+	fn partition_chunk(&self, first_id: usize, agents: &[Agent], halo_width: f32) -> (Vec<Vec<Id>>, Vec<Vec<Id>>) {
+		let mut members = vec![Vec::new(); self.region_count()];
+		let mut halos = vec![Vec::new(); self.region_count()];
+		for (index, agent) in agents.iter().enumerate() {
+			let id = Id::from(first_id + index);
+			let (column, row) = self.cell(agent.position);
+			members[row * self.columns + column].push(id);
+
+			// most agents are far enough away from the border of their region to not be in any halo
+			let offset = agent.position - Vector::new(column as f32, row as f32) * self.region_size;
+			let is_inside = offset.cmpgt(Vector::splat(halo_width)).all()
+				&& offset.cmplt(self.region_size - Vector::splat(halo_width)).all();
+			if is_inside {
+				continue;
+			}
+
+			// every other region that is close enough to the agent gets it in its halo
+			let (first_column, first_row) = self.cell(agent.position - Vector::splat(halo_width));
+			let (last_column, last_row) = self.cell(agent.position + Vector::splat(halo_width));
+			for other_row in first_row..=last_row {
+				for other_column in first_column..=last_column {
+					let is_other = (other_column, other_row) != (column, row);
+					if is_other && (self.distance_to_region(agent.position, (other_column, other_row)) <= halo_width) {
+						halos[other_row * self.columns + other_column].push(id);
+					}
+				}
+			}
+		}
+		(members, halos)
+	}
+}
+
+/// Merge two lists of distinct [`Id`]s that are sorted already
+fn merge_sorted(a: &[Id], b: &[Id]) -> Vec<Id> {
+	let mut merged = Vec::with_capacity(a.len() + b.len());
+	let (mut a, mut b) = (a.iter().peekable(), b.iter().peekable());
+	while let (Some(&&next_a), Some(&&next_b)) = (a.peek(), b.peek()) {
+		if next_a < next_b {
+			merged.push(next_a);
+			a.next();
+		} else {
+			merged.push(next_b);
+			b.next();
+		}
+	}
+	merged.extend(a);
+	merged.extend(b);
+	merged
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use rand::rngs::SmallRng;
+	use rand::SeedableRng;
+
+	#[test]
+	fn should_put_every_agent_in_reach_into_the_neighborhood() {
+		let bounds = Vector::new(200.0, 100.0);
+		let halo_width = 10.0;
+		let mut random_generator = SmallRng::seed_from_u64(42);
+		let agents = (0..2000)
+			.map(|_| Agent::random(bounds, &mut random_generator))
+			.collect::<Vec<_>>();
+		let grid = ShardGrid::new(bounds, 8);
+		assert_eq!((4, 2), (grid.columns, grid.rows));
+
+		let regions = grid.partition(&agents, halo_width);
+		assert_eq!(
+			agents.len(),
+			regions.iter().map(|region| region.members.len()).sum::<usize>()
+		);
+		for region in &regions {
+			assert!(region.neighborhood.windows(2).all(|pair| pair[0] < pair[1]));
+			for &member in &region.members {
+				let in_reach = (0..agents.len())
+					.map(Id::from)
+					.filter(|&other| agents[member].distance(&agents[other]) <= halo_width);
+				for other in in_reach {
+					assert!(region.neighborhood.binary_search(&other).is_ok());
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,44 @@
+use crate::id::Id;
+use serde::Serialize;
+use std::ops::Index;
+
+/// Statistics that the [`World`](crate::world::World) collects while simulating.
+#[derive(Clone, Default, Serialize)]
+pub struct Statistics {
+	/// How often somebody got tagged
+	pub tag_count: usize,
+	/// Iteration in which the last successful tag happened
+	pub last_tag_iteration: Option<usize>,
+	/// For how many consecutive iterations "it" hasn't seen any other agent
+	pub iterations_it_saw_nobody: usize,
+	/// Per agent statistics, indexed by [`Id`]
+	pub agents: Vec<AgentStatistics>,
+}
+
+impl Statistics {
+	pub fn new(agent_count: usize) -> Self {
+		Self {
+			agents: vec![AgentStatistics::default(); agent_count],
+			..Default::default()
+		}
+	}
+}
+
+impl Index<Id> for Statistics {
+	type Output = AgentStatistics;
+
+	fn index(&self, id: Id) -> &Self::Output {
+		&self.agents[id]
+	}
+}
+
+/// Statistics of one single agent.
+#[derive(Clone, Default, Serialize)]
+pub struct AgentStatistics {
+	/// How many other agents this agent has tagged
+	pub tags: usize,
+	/// How often this agent was tagged by others
+	pub times_tagged: usize,
+	/// How many iterations this agent has spent as "it"
+	pub iterations_as_it: usize,
+}
@@ -0,0 +1,365 @@
+use crate::agent::Agent;
+use crate::event::Event;
+use crate::id::Id;
+use crate::physics::Physics;
+use crate::types::{rotate_by_angle, Vector};
+use crate::viewer::Viewer;
+use crate::world::{World, WorldSnapshot};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Renders a [`WorldSnapshot`], or a window of consecutive snapshots, as SVG.
+///
+/// The last snapshot is drawn in full: Agents with their heading, field of view and range. All snapshots before it
+/// contribute the trails of the agents and the tags that happened during the window.
+#[derive(Clone, Copy, Debug)]
+pub struct SvgRenderer {
+	bounds: Vector,
+	physics: Physics,
+}
+
+impl SvgRenderer {
+	const AGENT_RADIUS: f32 = 3.0;
+
+	pub fn new(bounds: Vector, physics: Physics) -> Self {
+		Self { bounds, physics }
+	}
+
+	/// Space around the playing field
+	fn margin(&self) -> f32 {
+		2.0 * self.physics.range
+	}
+
+	/// The cones are only drawn up to this length, as the view distance is often unlimited
+	fn view_cone_length(&self) -> f32 {
+		(4.0 * self.physics.range).min(self.physics.view_distance)
+	}
+
+	fn arrow_length(&self) -> f32 {
+		2.0 * self.physics.range
+	}
+
+	/// Render the given snapshots, which are expected to be in order
+	pub fn render(&self, snapshots: &[WorldSnapshot]) -> String {
+		let mut svg = String::new();
+		// writing into a String can't fail
+		let _ = self.write_svg(&mut svg, snapshots);
+		svg
+	}
+
+	fn write_svg(&self, svg: &mut String, snapshots: &[WorldSnapshot]) -> std::fmt::Result {
+		let margin = self.margin();
+		let (width, height) = (self.bounds.x + 2.0 * margin, self.bounds.y + 2.0 * margin);
+		writeln!(
+			svg,
+			r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="{:.2} {:.2} {width:.2} {height:.2}">"#,
+			-margin, -margin
+		)?;
+		// one arrow head per color, as not all renderers support `context-stroke` yet
+		write!(svg, "<defs>")?;
+		for color in ["black", "red", "green"] {
+			write!(
+				svg,
+				r#"<marker id="arrow-{color}" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="6" markerHeight="6" orient="auto"><path d="M 0 0 L 10 5 L 0 10 z" fill="{color}"/></marker>"#
+			)?;
+		}
+		writeln!(svg, "</defs>")?;
+		writeln!(
+			svg,
+			r##"<rect x="0" y="0" width="{:.2}" height="{:.2}" fill="#f4f4f4" stroke="#333333"/>"##,
+			self.bounds.x, self.bounds.y
+		)?;
+
+		let last = match snapshots.last() {
+			Some(last) => last,
+			None => return writeln!(svg, "</svg>"),
+		};
+
+		self.write_trails(svg, snapshots)?;
+		for (index, agent) in last.agents.iter().enumerate() {
+			self.write_view_cone(svg, agent)?;
+			let center = self.point(agent.position);
+			writeln!(
+				svg,
+				r##"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="none" stroke="#999999" stroke-dasharray="2 2"/>"##,
+				center.x, center.y, self.physics.range
+			)?;
+
+			let color = agent_color(last, Id::from(index));
+			let tip = self.point(agent.position + rotate_by_angle(Vector::X * self.arrow_length(), agent.heading));
+			writeln!(
+				svg,
+				r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="{color}" marker-end="url(#arrow-{color})"/>"#,
+				center.x, center.y, tip.x, tip.y
+			)?;
+			writeln!(
+				svg,
+				r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="{color}"/>"#,
+				center.x,
+				center.y,
+				Self::AGENT_RADIUS
+			)?;
+		}
+		self.write_tags(svg, snapshots)?;
+		writeln!(
+			svg,
+			r#"<text x="0" y="{:.2}" font-family="sans-serif" font-size="12">Iteration: {}</text>"#,
+			-margin / 3.0,
+			last.iteration
+		)?;
+
+		writeln!(svg, "</svg>")
+	}
+
+	fn write_trails(&self, svg: &mut String, snapshots: &[WorldSnapshot]) -> std::fmt::Result {
+		if snapshots.len() < 2 {
+			return Ok(());
+		}
+
+		let agent_count = snapshots
+			.iter()
+			.map(|snapshot| snapshot.agents.len())
+			.min()
+			.unwrap_or(0);
+		for index in 0..agent_count {
+			let points = snapshots
+				.iter()
+				.map(|snapshot| {
+					let point = self.point(snapshot.agents[index].position);
+					format!("{:.2},{:.2}", point.x, point.y)
+				})
+				.collect::<Vec<_>>()
+				.join(" ");
+			writeln!(
+				svg,
+				r##"<polyline points="{points}" fill="none" stroke="#666666" stroke-opacity="0.5"/>"##
+			)?;
+		}
+
+		Ok(())
+	}
+
+	fn write_view_cone(&self, svg: &mut String, agent: &Agent) -> std::fmt::Result {
+		let half_angle = (self.physics.field_of_view_angle / 2.0).min(PI);
+		let center = self.point(agent.position);
+		let length = self.view_cone_length();
+		if half_angle >= PI {
+			// an arc from a point back to itself isn't drawn at all, so the whole surrounding is a circle
+			return writeln!(
+				svg,
+				r##"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="#ffcc00" fill-opacity="0.15" stroke="none"/>"##,
+				center.x, center.y, length,
+			);
+		}
+		let edge = |angle| self.point(agent.position + rotate_by_angle(Vector::X * length, angle));
+		let (start, end) = (edge(agent.heading - half_angle), edge(agent.heading + half_angle));
+		let large_arc = u8::from(half_angle > (PI / 2.0));
+		// sweep flag 0, because positive angles in the simulation are counterclockwise on screen, unlike in SVG
+		writeln!(
+			svg,
+			r##"<path d="M {:.2} {:.2} L {:.2} {:.2} A {length:.2} {length:.2} 0 {large_arc} 0 {:.2} {:.2} Z" fill="#ffcc00" fill-opacity="0.15" stroke="none"/>"##,
+			center.x, center.y, start.x, start.y, end.x, end.y,
+		)
+	}
+
+	/// Mark where somebody got tagged, labelled with the iteration
+	fn write_tags(&self, svg: &mut String, snapshots: &[WorldSnapshot]) -> std::fmt::Result {
+		for snapshot in snapshots {
+			for event in &snapshot.events {
+				let Event::Tag { iteration, tagged, .. } = *event;
+				let point = self.point(snapshot.agents[tagged].position);
+				let size = Self::AGENT_RADIUS * 2.0;
+				writeln!(
+					svg,
+					r##"<path d="M {:.2} {:.2} l {size:.2} {size:.2} m 0 {:.2} l {:.2} {size:.2}" stroke="#cc0000" stroke-width="2"/>"##,
+					point.x - size / 2.0,
+					point.y - size / 2.0,
+					-size,
+					-size,
+				)?;
+				writeln!(
+					svg,
+					r##"<text x="{:.2}" y="{:.2}" font-family="sans-serif" font-size="8" fill="#cc0000">{iteration}</text>"##,
+					point.x + size,
+					point.y - size
+				)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Position in the SVG, where y points down instead of up
+	fn point(&self, position: Vector) -> Vector {
+		Vector::new(position.x, self.bounds.y - position.y)
+	}
+}
+
+fn agent_color(snapshot: &WorldSnapshot, id: Id) -> &'static str {
+	if id == snapshot.it {
+		"red"
+	} else if id == snapshot.previous_it {
+		"green"
+	} else {
+		"black"
+	}
+}
+
+/// Viewer that keeps the last snapshots and writes them as SVG with an [`SvgRenderer`] once the simulation is finished
+pub struct SvgViewer {
+	renderer: SvgRenderer,
+	path: PathBuf,
+	window: usize,
+	snapshots: Mutex<VecDeque<WorldSnapshot>>,
+}
+
+impl SvgViewer {
+	/// Only write the final snapshot to the given file
+	pub fn new(renderer: SvgRenderer, path: &Path) -> Self {
+		Self {
+			renderer,
+			path: path.to_path_buf(),
+			window: 1,
+			snapshots: Default::default(),
+		}
+	}
+
+	/// Draw trails and tags of this many iterations up to the final one
+	pub fn with_window(mut self, window: usize) -> Self {
+		self.window = window.max(1);
+		self
+	}
+
+	fn record(&self, world: &World) {
+		let mut snapshots = self.snapshots.lock().expect("Lock was poisoned");
+		if let Some(last) = snapshots.back() {
+			if last.iteration == world.iteration() {
+				return;
+			}
+			// a new round started, so the trails would jump
+			if last.iteration > world.iteration() {
+				snapshots.clear();
+			}
+		}
+
+		if snapshots.len() == self.window {
+			snapshots.pop_front();
+		}
+		snapshots.push_back(world.snapshot());
+	}
+}
+
+impl Viewer for SvgViewer {
+	fn iteration(&self, world: &World) {
+		if self.window > 1 {
+			self.record(world);
+		}
+	}
+
+	fn finished(&self, world: &World) {
+		self.record(world);
+
+		let mut snapshots = self.snapshots.lock().expect("Lock was poisoned");
+		let svg = self.renderer.render(snapshots.make_contiguous());
+		if let Err(error) = std::fs::write(&self.path, svg) {
+			eprintln!("Failed to write {}: {error}", self.path.display());
+		}
+	}
+
+	fn run(&self) {
+		// Nothing to do, the SVG is written from the simulation thread
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::sync::Arc;
+
+	fn snapshot(iteration: usize, x: f32, events: Vec<Event>) -> WorldSnapshot {
+		WorldSnapshot {
+			agents: Arc::new(vec![
+				Agent {
+					position: Vector::new(x, 10.0),
+					heading: 0.0,
+				},
+				Agent {
+					position: Vector::new(50.0, 50.0),
+					heading: PI,
+				},
+			]),
+			iteration,
+			it: Id::from(0),
+			previous_it: Id::from(1),
+			events,
+			targets: Default::default(),
+		}
+	}
+
+	#[test]
+	fn should_render_trails_and_tags() {
+		let renderer = SvgRenderer::new(Vector::new(100.0, 100.0), Physics::default());
+		let tag = Event::Tag {
+			iteration: 1,
+			tagger: Id::from(1),
+			tagged: Id::from(0),
+		};
+		let snapshots = vec![
+			snapshot(1, 10.0, vec![tag]),
+			snapshot(2, 15.0, Vec::new()),
+			snapshot(3, 20.0, Vec::new()),
+		];
+
+		let svg = renderer.render(&snapshots);
+
+		assert!(svg.starts_with("<svg"));
+		assert!(svg.trim_end().ends_with("</svg>"));
+		// the y axis is flipped
+		assert!(svg.contains(r#"points="10.00,90.00 15.00,90.00 20.00,90.00""#));
+		assert_eq!(2, svg.matches("<polyline").count());
+		assert_eq!(2, svg.matches("marker-end").count());
+		assert!(svg.contains(">1</text>"));
+		assert!(svg.contains("Iteration: 3"));
+	}
+
+	#[test]
+	fn should_draw_a_full_field_of_view_as_circle() {
+		let physics = Physics {
+			field_of_view_angle: 2.0 * PI,
+			..Physics::default()
+		};
+		let renderer = SvgRenderer::new(Vector::new(100.0, 100.0), physics);
+
+		let svg = renderer.render(&[snapshot(1, 10.0, Vec::new())]);
+
+		assert_eq!(2, svg.matches(r##"fill="#ffcc00""##).count());
+		assert!(!svg.contains(" A "));
+	}
+
+	#[test]
+	fn should_size_the_margin_and_view_cones_by_the_physics() {
+		let physics = Physics {
+			field_of_view_angle: 2.0 * PI,
+			range: 5.0,
+			..Physics::default()
+		};
+		let renderer = SvgRenderer::new(Vector::new(100.0, 100.0), physics);
+
+		let svg = renderer.render(&[snapshot(1, 10.0, Vec::new())]);
+		assert!(svg.contains(r#"width="120" height="120" viewBox="-10.00 -10.00"#));
+		assert!(svg.contains(r##"r="20.00" fill="#ffcc00""##));
+
+		let renderer = SvgRenderer::new(
+			Vector::new(100.0, 100.0),
+			Physics {
+				view_distance: 8.0,
+				..physics
+			},
+		);
+		let svg = renderer.render(&[snapshot(1, 10.0, Vec::new())]);
+		assert!(svg.contains(r##"r="8.00" fill="#ffcc00""##));
+	}
+}
@@ -0,0 +1,373 @@
+use crate::behavior::registry::BehaviorRegistry;
+use crate::game::{ClassicTag, MatchResult};
+use crate::physics::Physics;
+use crate::termination::{IterationLimit, TerminationCondition, Victory};
+use crate::types::{degrees_to_radians, Vector};
+use crate::world::World;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::Serialize;
+use std::io::Write;
+use std::ops::Add;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Runs the cartesian product of all given parameter values, once per seed.
+///
+/// Every run is simulated sequentially, but the runs themselves are spread over all cores using rayon.
+#[derive(Clone)]
+pub struct Sweep {
+	pub agent_counts: Vec<usize>,
+	pub bounds: Vec<Vector>,
+	pub behaviors: Vec<String>,
+	pub maximum_velocities: Vec<f32>,
+	pub ranges: Vec<f32>,
+	pub field_of_view_degrees: Vec<f32>,
+	pub seeds: Vec<u64>,
+	pub iterations: usize,
+	pub tag_limit: Option<usize>,
+}
+
+/// Parameters of a single run of a [`Sweep`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunParameters {
+	pub agent_count: usize,
+	pub bounds: Vector,
+	pub behavior: String,
+	pub physics: Physics,
+	pub field_of_view_degrees: f32,
+	pub seed: u64,
+}
+
+/// Result of a single run of a [`Sweep`], this is one row in the results file.
+#[derive(Clone, Debug, Serialize)]
+pub struct SweepRow {
+	pub agent_count: usize,
+	pub width: f32,
+	pub height: f32,
+	pub behavior: String,
+	pub maximum_velocity: f32,
+	pub range: f32,
+	pub field_of_view_degrees: f32,
+	pub seed: u64,
+	pub iterations: usize,
+	pub reason: String,
+	pub winner: Option<usize>,
+	pub tag_count: usize,
+	/// How many different agents were "it" at some point
+	pub distinct_its: usize,
+	/// Longest time a single agent has spent as "it"
+	pub maximum_iterations_as_it: usize,
+	pub duration_milliseconds: f64,
+}
+
+impl Sweep {
+	/// All combinations of parameters that will be run
+	pub fn runs(&self) -> Vec<RunParameters> {
+		let mut runs = Vec::new();
+		for &agent_count in &self.agent_counts {
+			for &bounds in &self.bounds {
+				for behavior in &self.behaviors {
+					for &maximum_velocity in &self.maximum_velocities {
+						for &range in &self.ranges {
+							for &field_of_view_degrees in &self.field_of_view_degrees {
+								for &seed in &self.seeds {
+									runs.push(RunParameters {
+										agent_count,
+										bounds,
+										behavior: behavior.clone(),
+										physics: Physics {
+											field_of_view_angle: degrees_to_radians(field_of_view_degrees),
+											maximum_velocity,
+											range,
+											..Physics::default()
+										},
+										field_of_view_degrees,
+										seed,
+									});
+								}
+							}
+						}
+					}
+				}
+			}
+		}
+
+		runs
+	}
+
+	/// Perform all runs in parallel
+	pub fn run(&self, registry: &BehaviorRegistry) -> Result<Vec<SweepRow>, String> {
+		for behavior in &self.behaviors {
+			registry.create(behavior)?;
+		}
+		if self.agent_counts.contains(&0) {
+			return Err("Agent counts must be positive".to_string());
+		}
+		if let Some(bounds) = self.bounds.iter().find(|bounds| !(bounds.x > 0.0 && bounds.y > 0.0)) {
+			return Err(format!(
+				"The playing field must not be empty, got {}x{}",
+				bounds.x, bounds.y
+			));
+		}
+		if let Some(velocity) = self
+			.maximum_velocities
+			.iter()
+			.find(|&&velocity| !(velocity > 0.0 && velocity.is_finite()))
+		{
+			return Err(format!("Maximum velocities must be positive, got {velocity}"));
+		}
+		if let Some(range) = self.ranges.iter().find(|&&range| !(range > 0.0 && range.is_finite())) {
+			return Err(format!("Ranges must be positive, got {range}"));
+		}
+		if let Some(degrees) = self
+			.field_of_view_degrees
+			.iter()
+			.find(|&degrees| !(0.0..=360.0).contains(degrees))
+		{
+			return Err(format!(
+				"The field of view must be between 0 and 360 degrees, got {degrees}"
+			));
+		}
+
+		Ok(self
+			.runs()
+			.par_iter()
+			.map(|parameters| self.run_single(registry, parameters))
+			.collect())
+	}
+
+	fn run_single(&self, registry: &BehaviorRegistry, parameters: &RunParameters) -> SweepRow {
+		let start = Instant::now();
+		let mut random_generator = SmallRng::seed_from_u64(parameters.seed);
+		let behaviors = (0..parameters.agent_count)
+			.map(|_| {
+				registry
+					.create(&parameters.behavior)
+					.expect("Behaviors were validated before")
+			})
+			.collect();
+		let mut world = World::random_with_behaviors(parameters.bounds, behaviors, false, &mut random_generator)
+			.with_physics(parameters.physics);
+
+		let game_mode = Arc::new(ClassicTag {
+			tag_limit: self.tag_limit,
+		});
+		let mut termination: Vec<Box<dyn TerminationCondition>> = vec![
+			Box::new(IterationLimit(self.iterations)),
+			Box::new(Victory(game_mode.clone())),
+		];
+		let reason = world.simulate_until(&mut termination);
+		let result = MatchResult::new(&world, game_mode.as_ref(), reason);
+		let agents = &result.statistics.agents;
+
+		SweepRow {
+			agent_count: parameters.agent_count,
+			width: parameters.bounds.x,
+			height: parameters.bounds.y,
+			behavior: parameters.behavior.clone(),
+			maximum_velocity: parameters.physics.maximum_velocity,
+			range: parameters.physics.range,
+			field_of_view_degrees: parameters.field_of_view_degrees,
+			seed: parameters.seed,
+			iterations: result.iterations,
+			reason: result.reason.to_string(),
+			winner: result.winner.map(usize::from),
+			tag_count: result.statistics.tag_count,
+			distinct_its: agents.iter().filter(|agent| agent.iterations_as_it > 0).count(),
+			maximum_iterations_as_it: agents.iter().map(|agent| agent.iterations_as_it).max().unwrap_or(0),
+			duration_milliseconds: start.elapsed().as_secs_f64() * 1000.0,
+		}
+	}
+}
+
+/// Format of the results file of a [`Sweep`]
+#[derive(Clone, Copy, Debug)]
+pub enum OutputFormat {
+	Csv,
+	Json,
+}
+
+impl FromStr for OutputFormat {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		use OutputFormat::*;
+		match text {
+			"csv" => Ok(Csv),
+			"json" => Ok(Json),
+			_ => Err(format!("Invalid output format: {text}")),
+		}
+	}
+}
+
+impl OutputFormat {
+	/// Write all rows in this format
+	pub fn write(&self, rows: &[SweepRow], writer: impl Write) -> Result<(), String> {
+		match self {
+			OutputFormat::Csv => {
+				let mut csv_writer = csv::Writer::from_writer(writer);
+				for row in rows {
+					csv_writer.serialize(row).map_err(|error| error.to_string())?;
+				}
+				csv_writer.flush().map_err(|error| error.to_string())
+			}
+			OutputFormat::Json => serde_json::to_writer_pretty(writer, rows).map_err(|error| error.to_string()),
+		}
+	}
+}
+
+/// List of values for a parameter of a [`Sweep`].
+///
+/// Parsed from a comma separated list where every element is either a single value
+/// or an inclusive range with a step size, e.g. `10,100..=500:100`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Values<Value>(pub Vec<Value>);
+
+impl<Value> FromStr for Values<Value>
+where
+	Value: FromStr + Copy + PartialOrd + Add<Output = Value> + Default,
+{
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let parse = |text: &str| {
+			text.trim()
+				.parse::<Value>()
+				.map_err(|_| format!("Invalid value: {text}"))
+		};
+
+		let mut values = Vec::new();
+		for element in text.split(',') {
+			let (start, rest) = match element.split_once("..=") {
+				Some(range) => range,
+				None => {
+					values.push(parse(element)?);
+					continue;
+				}
+			};
+
+			let (end, step) = rest
+				.split_once(':')
+				.ok_or_else(|| format!("Range without step size: {element}"))?;
+			let (start, end, step) = (parse(start)?, parse(end)?, parse(step)?);
+			if step <= Value::default() {
+				return Err(format!("Step size must be positive: {element}"));
+			}
+
+			let mut value = start;
+			while value <= end {
+				values.push(value);
+				value = value + step;
+			}
+		}
+
+		Ok(Self(values))
+	}
+}
+
+/// List of playing field sizes, parsed from a comma separated list like `500x500,1000x800`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundsValues(pub Vec<Vector>);
+
+impl FromStr for BoundsValues {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		text.split(',')
+			.map(|element| {
+				let invalid = || format!("Invalid bounds: {element}, expected <width>x<height>");
+				let (width, height) = element.split_once('x').ok_or_else(invalid)?;
+				let width = width.trim().parse::<f32>().map_err(|_| invalid())?;
+				let height = height.trim().parse::<f32>().map_err(|_| invalid())?;
+				Ok(Vector::new(width, height))
+			})
+			.collect::<Result<_, _>>()
+			.map(Self)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_parse_values_and_ranges() {
+		assert_eq!(Values(vec![1, 5, 7, 9]), "1,5..=9:2".parse::<Values<usize>>().unwrap());
+		assert_eq!(
+			Values(vec![2.5, 5.0, 7.5]),
+			"2.5..=7.5:2.5".parse::<Values<f32>>().unwrap()
+		);
+		assert!("1..=5".parse::<Values<usize>>().is_err());
+		assert!("1..=5:0".parse::<Values<usize>>().is_err());
+		assert_eq!(
+			BoundsValues(vec![Vector::new(500.0, 400.0)]),
+			"500x400".parse::<BoundsValues>().unwrap()
+		);
+	}
+
+	#[test]
+	fn should_run_cartesian_product_of_parameters() {
+		let sweep = Sweep {
+			agent_counts: vec![2, 5],
+			bounds: vec![Vector::new(100.0, 100.0)],
+			behaviors: vec!["default".to_string(), "chasing".to_string()],
+			maximum_velocities: vec![5.0],
+			ranges: vec![5.0, 10.0],
+			field_of_view_degrees: vec![200.0],
+			seeds: vec![1, 2, 3],
+			iterations: 10,
+			tag_limit: None,
+		};
+
+		let rows = sweep.run(&BehaviorRegistry::default()).unwrap();
+
+		assert_eq!(2 * 2 * 2 * 3, rows.len());
+		assert!(rows.iter().all(|row| row.iterations == 10));
+
+		let mut output = Vec::new();
+		OutputFormat::Csv.write(&rows, &mut output).unwrap();
+		let output = String::from_utf8(output).unwrap();
+		assert_eq!(rows.len() + 1, output.lines().count());
+	}
+
+	#[test]
+	fn should_reject_invalid_parameters() {
+		let sweep = Sweep {
+			agent_counts: vec![2],
+			bounds: vec![Vector::new(100.0, 100.0)],
+			behaviors: vec!["default".to_string()],
+			maximum_velocities: vec![5.0],
+			ranges: vec![10.0],
+			field_of_view_degrees: vec![200.0],
+			seeds: vec![1],
+			iterations: 10,
+			tag_limit: None,
+		};
+		let registry = BehaviorRegistry::default();
+		assert!(sweep.run(&registry).is_ok());
+
+		for invalid in [
+			Sweep {
+				bounds: vec![Vector::new(0.0, 500.0)],
+				..sweep.clone()
+			},
+			Sweep {
+				maximum_velocities: vec![-1.0],
+				..sweep.clone()
+			},
+			Sweep {
+				ranges: vec![0.0],
+				..sweep.clone()
+			},
+			Sweep {
+				field_of_view_degrees: vec![361.0],
+				..sweep.clone()
+			},
+		] {
+			assert!(invalid.run(&registry).is_err());
+		}
+	}
+}
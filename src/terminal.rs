@@ -0,0 +1,340 @@
+use crate::agent::Agent;
+use crate::id::Id;
+use crate::runner::RunnerControl;
+use crate::statistics::Statistics;
+use crate::types::{normalize_radians, Vector};
+use crate::viewer::Viewer;
+use crate::world::{World, WorldSnapshot};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::f32::consts::PI;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Viewer that draws the playing field into the terminal, e.g. when running over SSH.
+///
+/// The arena is scaled to the size of the terminal with a statistics sidebar next to it.
+/// The simulation can be controlled with the keyboard: `space` pauses and resumes, `s` runs a single step,
+/// `+` and `-` change the speed and `q` stops the simulation and quits.
+/// Once the simulation is finished, [`Viewer::run`] keeps showing its final state until the user quits.
+pub struct TerminalViewer {
+	control: RunnerControl,
+	frame: Mutex<Frame>,
+}
+
+/// Everything needed to draw the terminal once
+#[derive(Clone, Default)]
+struct Frame {
+	snapshot: Option<WorldSnapshot>,
+	statistics: Statistics,
+	bounds: Vector,
+	last_update: Option<Instant>,
+	finished: bool,
+}
+
+impl TerminalViewer {
+	/// Maximum rate at which snapshots are taken for drawing
+	const FRAME_INTERVAL: Duration = Duration::from_millis(33);
+	/// Width of the statistics sidebar in characters
+	const SIDEBAR_WIDTH: u16 = 32;
+
+	pub fn new(control: RunnerControl) -> Self {
+		Self {
+			control,
+			frame: Default::default(),
+		}
+	}
+
+	fn update_frame(&self, world: &World, force: bool) {
+		let mut frame = self.frame.lock().expect("Lock was poisoned");
+		let now = Instant::now();
+		let is_due = frame
+			.last_update
+			.map(|last_update| (now - last_update) >= Self::FRAME_INTERVAL)
+			.unwrap_or(true);
+		if !(force || is_due) {
+			return;
+		}
+
+		frame.snapshot = Some(world.snapshot());
+		frame.statistics = world.statistics().clone();
+		frame.bounds = world.bounds();
+		frame.last_update = Some(now);
+	}
+
+	/// Handle a key press, returns `false` if the viewer should quit
+	fn handle_key(&self, key: KeyEvent) -> bool {
+		if key.kind == KeyEventKind::Release {
+			return true;
+		}
+
+		match key.code {
+			KeyCode::Char('q') | KeyCode::Esc => return false,
+			KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+			KeyCode::Char(' ') => self.control.toggle_pause(),
+			KeyCode::Char('s') | KeyCode::Right => {
+				self.control.pause();
+				self.control.step();
+			}
+			KeyCode::Char('+') => self.control.speed_up(),
+			KeyCode::Char('-') => self.control.slow_down(),
+			_ => {}
+		}
+
+		true
+	}
+
+	fn run_event_loop(&self, output: &mut impl Write) -> std::io::Result<()> {
+		let mut last_drawn = None;
+		loop {
+			if crossterm::event::poll(Duration::from_millis(20))? {
+				match crossterm::event::read()? {
+					Event::Key(key) if !self.handle_key(key) => return Ok(()),
+					Event::Resize(_, _) => last_drawn = None,
+					_ => {}
+				}
+			}
+
+			let (paused, ticks_per_second) = (self.control.is_paused(), self.control.ticks_per_second());
+			// the frame is copied, so the simulation doesn't wait for the terminal while it is drawn
+			let frame = {
+				let frame = self.frame.lock().expect("Lock was poisoned");
+				let status = Status {
+					paused,
+					finished: frame.finished,
+					ticks_per_second,
+				};
+				let current = (frame.last_update, status);
+				(last_drawn != Some(current)).then(|| (frame.clone(), status, current))
+			};
+			if let Some((frame, status, current)) = frame {
+				draw(output, &frame, status)?;
+				last_drawn = Some(current);
+			}
+		}
+	}
+}
+
+impl Viewer for TerminalViewer {
+	fn iteration(&self, world: &World) {
+		self.update_frame(world, false);
+	}
+
+	fn finished(&self, world: &World) {
+		self.update_frame(world, true);
+		self.frame.lock().expect("Lock was poisoned").finished = true;
+	}
+
+	fn run(&self) {
+		let mut output = std::io::stdout();
+		let result = crossterm::terminal::enable_raw_mode()
+			.and_then(|_| execute!(output, EnterAlternateScreen, Hide))
+			.and_then(|_| self.run_event_loop(&mut output));
+
+		let _ = execute!(output, ResetColor, Show, LeaveAlternateScreen);
+		let _ = crossterm::terminal::disable_raw_mode();
+		if let Err(error) = result {
+			eprintln!("Terminal viewer failed: {error}");
+		}
+
+		self.control.stop();
+	}
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Status {
+	paused: bool,
+	finished: bool,
+	ticks_per_second: f64,
+}
+
+/// Glyph showing the direction an agent is heading in
+fn heading_glyph(agent: &Agent) -> char {
+	const GLYPHS: [char; 8] = ['→', '↗', '↑', '↖', '←', '↙', '↓', '↘'];
+	let octant = (normalize_radians(agent.heading + PI / 8.0) / (PI / 4.0)) as usize;
+	GLYPHS[octant % GLYPHS.len()]
+}
+
+fn agent_color(snapshot: &WorldSnapshot, id: Id) -> Color {
+	if id == snapshot.it {
+		Color::Red
+	} else if id == snapshot.previous_it {
+		Color::Green
+	} else {
+		Color::White
+	}
+}
+
+fn draw(output: &mut impl Write, frame: &Frame, status: Status) -> std::io::Result<()> {
+	let (columns, rows) = crossterm::terminal::size()?;
+	let mut grid = Grid::new(columns, rows);
+
+	match &frame.snapshot {
+		Some(snapshot) => draw_snapshot(&mut grid, frame, snapshot, status),
+		None => grid.print(0, 0, "Waiting for the simulation to start...", Color::White),
+	}
+
+	grid.write(output)
+}
+
+fn draw_snapshot(grid: &mut Grid, frame: &Frame, snapshot: &WorldSnapshot, status: Status) {
+	// The arena including its border, the rest of the width is for the sidebar
+	let arena_columns = grid.columns.saturating_sub(TerminalViewer::SIDEBAR_WIDTH).max(3);
+	let inner_columns = arena_columns - 2;
+	let inner_rows = grid.rows.saturating_sub(2).max(1);
+
+	let horizontal = "─".repeat(inner_columns as usize);
+	grid.print(0, 0, &format!("┌{horizontal}┐"), Color::DarkGrey);
+	for row in 1..=inner_rows {
+		grid.set(0, row, '│', Color::DarkGrey);
+		grid.set(arena_columns - 1, row, '│', Color::DarkGrey);
+	}
+	grid.print(0, inner_rows + 1, &format!("└{horizontal}┘"), Color::DarkGrey);
+
+	// draw "it" and the previous "it" last, so other agents don't hide them
+	let ids = (0..snapshot.agents.len())
+		.map(Id::from)
+		.filter(|&id| (id != snapshot.it) && (id != snapshot.previous_it))
+		.chain([snapshot.previous_it, snapshot.it]);
+	for id in ids {
+		let agent = &snapshot.agents[id];
+		let column = scale(agent.position.x, frame.bounds.x, inner_columns);
+		// y points up in the simulation, but down in the terminal
+		let row = scale(frame.bounds.y - agent.position.y, frame.bounds.y, inner_rows);
+		grid.set(column + 1, row + 1, heading_glyph(agent), agent_color(snapshot, id));
+	}
+
+	for (row, line) in sidebar_lines(snapshot, &frame.statistics, status).iter().enumerate() {
+		grid.print(arena_columns + 1, row as u16, line, Color::White);
+	}
+}
+
+/// Characters and their colors for the whole terminal, so every frame can be written in one go without flickering
+struct Grid {
+	columns: u16,
+	rows: u16,
+	cells: Vec<(char, Color)>,
+}
+
+impl Grid {
+	fn new(columns: u16, rows: u16) -> Self {
+		Self {
+			columns,
+			rows,
+			cells: vec![(' ', Color::Reset); columns as usize * rows as usize],
+		}
+	}
+
+	fn set(&mut self, column: u16, row: u16, character: char, color: Color) {
+		if (column < self.columns) && (row < self.rows) {
+			self.cells[row as usize * self.columns as usize + column as usize] = (character, color);
+		}
+	}
+
+	fn print(&mut self, column: u16, row: u16, text: &str, color: Color) {
+		for (offset, character) in text.chars().enumerate() {
+			self.set(column.saturating_add(offset as u16), row, character, color);
+		}
+	}
+
+	fn write(&self, output: &mut impl Write) -> std::io::Result<()> {
+		let mut current_color = None;
+		for (row, cells) in self.cells.chunks(self.columns.max(1) as usize).enumerate() {
+			queue!(output, MoveTo(0, row as u16))?;
+			for &(character, color) in cells {
+				if current_color != Some(color) {
+					queue!(output, SetForegroundColor(color))?;
+					current_color = Some(color);
+				}
+				queue!(output, Print(character))?;
+			}
+		}
+
+		queue!(output, ResetColor)?;
+		output.flush()
+	}
+}
+
+/// Map a coordinate from `0..=bound` to a cell in `0..cells`
+fn scale(coordinate: f32, bound: f32, cells: u16) -> u16 {
+	let fraction = if bound > 0.0 { coordinate / bound } else { 0.0 };
+	((fraction * cells as f32) as u16).min(cells - 1)
+}
+
+fn sidebar_lines(snapshot: &WorldSnapshot, statistics: &Statistics, status: Status) -> Vec<String> {
+	let state = if status.finished {
+		"finished"
+	} else if status.paused {
+		"paused"
+	} else {
+		"running"
+	};
+	let speed = if status.ticks_per_second > 0.0 {
+		format!("{:.0} ticks/s", status.ticks_per_second)
+	} else {
+		"unlimited".to_string()
+	};
+
+	let mut lines = vec![
+		format!("Iteration: {}", snapshot.iteration),
+		format!("State: {state}"),
+		format!("Speed: {speed}"),
+		format!("It: {}", snapshot.it),
+		format!("Previously: {}", snapshot.previous_it),
+		format!("Tags: {}", statistics.tag_count),
+		match statistics.last_tag_iteration {
+			Some(iteration) => format!("Since last tag: {}", snapshot.iteration - iteration),
+			None => "Since last tag: -".to_string(),
+		},
+		String::new(),
+		"Least time as \"it\":".to_string(),
+	];
+
+	let mut ranking = statistics.agents.iter().enumerate().collect::<Vec<_>>();
+	ranking.sort_by_key(|(_, agent)| agent.iterations_as_it);
+	lines.extend(
+		ranking
+			.iter()
+			.take(10)
+			.map(|(index, agent)| format!("{}: {} ({} tags)", Id::from(*index), agent.iterations_as_it, agent.tags)),
+	);
+
+	lines.extend([
+		String::new(),
+		"space: pause/resume".to_string(),
+		"s: single step".to_string(),
+		"+/-: faster/slower".to_string(),
+		"q: quit".to_string(),
+	]);
+	lines
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::types::degrees_to_radians;
+
+	#[test]
+	fn should_pick_glyph_by_heading() {
+		let agent = |degrees| Agent {
+			position: Vector::ZERO,
+			heading: degrees_to_radians(degrees),
+		};
+
+		assert_eq!('→', heading_glyph(&agent(0.0)));
+		assert_eq!('→', heading_glyph(&agent(350.0)));
+		assert_eq!('↑', heading_glyph(&agent(95.0)));
+		assert_eq!('↙', heading_glyph(&agent(-135.0)));
+	}
+
+	#[test]
+	fn should_scale_into_cells() {
+		assert_eq!(0, scale(0.0, 500.0, 50));
+		assert_eq!(25, scale(250.0, 500.0, 50));
+		assert_eq!(49, scale(500.0, 500.0, 50));
+	}
+}
@@ -0,0 +1,167 @@
+use crate::game::GameMode;
+use crate::world::World;
+use serde::Serialize;
+use static_assertions::assert_obj_safe;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Decides when a simulation run is over.
+/// It is checked before every simulation step and the run ends with the first [`TerminationReason`] returned.
+pub trait TerminationCondition: Send {
+	fn check(&mut self, world: &World) -> Option<TerminationReason>;
+}
+
+assert_obj_safe!(TerminationCondition);
+
+/// Why a simulation run has ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TerminationReason {
+	IterationLimit,
+	TimeLimit,
+	Victory,
+	Stalemate,
+	/// The run was stopped from the outside
+	Stopped,
+}
+
+impl Display for TerminationReason {
+	fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+		use TerminationReason::*;
+		let description = match self {
+			IterationLimit => "iteration limit reached",
+			TimeLimit => "time limit reached",
+			Victory => "the game was decided",
+			Stalemate => "stalemate, \"it\" didn't see anybody for too long",
+			Stopped => "stopped",
+		};
+		formatter.write_str(description)
+	}
+}
+
+/// Ends the run after a fixed number of iterations.
+pub struct IterationLimit(pub usize);
+
+impl TerminationCondition for IterationLimit {
+	fn check(&mut self, world: &World) -> Option<TerminationReason> {
+		(world.iteration() >= self.0).then_some(TerminationReason::IterationLimit)
+	}
+}
+
+/// Ends the run after a given wall-clock time. The clock starts with the first check.
+pub struct TimeLimit {
+	limit: Duration,
+	start: Option<Instant>,
+}
+
+impl TimeLimit {
+	pub fn new(limit: Duration) -> Self {
+		Self { limit, start: None }
+	}
+}
+
+impl TerminationCondition for TimeLimit {
+	fn check(&mut self, _world: &World) -> Option<TerminationReason> {
+		let start = *self.start.get_or_insert_with(Instant::now);
+		(start.elapsed() >= self.limit).then_some(TerminationReason::TimeLimit)
+	}
+}
+
+/// Ends the run once the [`GameMode`] considers the game decided.
+pub struct Victory(pub Arc<dyn GameMode>);
+
+impl TerminationCondition for Victory {
+	fn check(&mut self, world: &World) -> Option<TerminationReason> {
+		self.0.is_decided(world).then_some(TerminationReason::Victory)
+	}
+}
+
+/// Ends the run if "it" hasn't seen any other agent for the given number of iterations.
+pub struct Stalemate(pub usize);
+
+impl TerminationCondition for Stalemate {
+	fn check(&mut self, world: &World) -> Option<TerminationReason> {
+		(world.statistics().iterations_it_saw_nobody >= self.0).then_some(TerminationReason::Stalemate)
+	}
+}
+
+/// Combines multiple conditions, the first one that is met ends the run.
+impl TerminationCondition for Vec<Box<dyn TerminationCondition>> {
+	fn check(&mut self, world: &World) -> Option<TerminationReason> {
+		self.iter_mut().find_map(|condition| condition.check(world))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::behavior::default::DefaultBehavior;
+	use crate::game::ClassicTag;
+	use crate::types::Vector;
+	use rand::rngs::SmallRng;
+	use rand::SeedableRng;
+
+	fn random_world() -> World {
+		World::random(
+			Vector::new(100.0, 100.0),
+			10,
+			DefaultBehavior::default,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		)
+	}
+
+	#[test]
+	fn should_stop_at_iteration_limit() {
+		let mut world = random_world();
+
+		let reason = world.simulate_until(&mut IterationLimit(42));
+
+		assert_eq!(TerminationReason::IterationLimit, reason);
+		assert_eq!(42, world.iteration());
+	}
+
+	#[test]
+	fn should_stop_at_first_met_condition() {
+		let mut world = random_world();
+		let mut conditions: Vec<Box<dyn TerminationCondition>> = vec![
+			Box::new(IterationLimit(100)),
+			Box::new(Victory(Arc::new(ClassicTag { tag_limit: Some(0) }))),
+		];
+
+		let reason = world.simulate_until(&mut conditions);
+
+		assert_eq!(TerminationReason::Victory, reason);
+		assert_eq!(0, world.iteration());
+	}
+
+	#[test]
+	fn should_stop_at_time_limit() {
+		let mut world = random_world();
+
+		let reason = world.simulate_until(&mut TimeLimit::new(Duration::ZERO));
+
+		assert_eq!(TerminationReason::TimeLimit, reason);
+	}
+
+	#[test]
+	fn should_stop_at_stalemate() {
+		// a single agent never sees anybody
+		let mut world = World::random(
+			Vector::new(100.0, 100.0),
+			1,
+			DefaultBehavior::default,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		);
+		let mut conditions: Vec<Box<dyn TerminationCondition>> =
+			vec![Box::new(IterationLimit(100)), Box::new(Stalemate(5))];
+
+		let reason = world.simulate_until(&mut conditions);
+
+		assert_eq!(TerminationReason::Stalemate, reason);
+		assert_eq!(5, world.iteration());
+		assert_eq!(5, world.statistics().iterations_it_saw_nobody);
+	}
+}
@@ -0,0 +1,306 @@
+use crate::behavior::registry::BehaviorRegistry;
+use crate::game::{ClassicTag, MatchResult};
+use crate::termination::{IterationLimit, TerminationCondition, Victory};
+use crate::types::Vector;
+use crate::world::World;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// Round-robin tournament between populations of different behaviors.
+///
+/// Every pair of behaviors plays one match per seed. In a match, both behaviors control the same
+/// number of agents and the behavior with the higher average score wins. The outcomes are turned
+/// into Elo ratings with bootstrapped confidence intervals.
+pub struct Tournament {
+	pub behaviors: Vec<String>,
+	pub seeds: Vec<u64>,
+	pub agents_per_behavior: usize,
+	pub bounds: Vector,
+	pub iterations: usize,
+	pub tag_limit: Option<usize>,
+}
+
+/// The outcome of one match between two behaviors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Game {
+	/// Index of the first behavior in [`Tournament::behaviors`]
+	pub first: usize,
+	/// Index of the second behavior in [`Tournament::behaviors`]
+	pub second: usize,
+	pub seed: u64,
+	/// Score of the first behavior: 1 for a win, 0.5 for a draw and 0 for a loss
+	pub outcome: f32,
+}
+
+impl Tournament {
+	/// Rating every behavior starts with
+	pub const INITIAL_RATING: f32 = 1500.0;
+	/// How strongly a single game changes the ratings
+	pub const K_FACTOR: f32 = 16.0;
+	/// How many times the games are resampled to calculate the confidence intervals
+	pub const BOOTSTRAP_SAMPLES: usize = 200;
+
+	/// Play all matches in parallel and rate the behaviors
+	pub fn run(&self, registry: &BehaviorRegistry) -> Result<TournamentResult, String> {
+		if self.behaviors.len() < 2 {
+			return Err("A tournament requires at least two behaviors".to_string());
+		}
+		if self.agents_per_behavior == 0 {
+			return Err("Every behavior needs at least one agent".to_string());
+		}
+		if !(self.bounds.x > 0.0 && self.bounds.y > 0.0 && self.bounds.is_finite()) {
+			return Err(format!(
+				"The playing field must be positive and finite, got {}x{}",
+				self.bounds.x, self.bounds.y
+			));
+		}
+		for behavior in &self.behaviors {
+			registry.create(behavior)?;
+		}
+
+		let pairings = (0..self.behaviors.len())
+			.flat_map(|first| ((first + 1)..self.behaviors.len()).map(move |second| (first, second)))
+			.flat_map(|(first, second)| self.seeds.iter().map(move |&seed| (first, second, seed)))
+			.collect::<Vec<_>>();
+
+		let games = pairings
+			.par_iter()
+			.map(|&(first, second, seed)| self.play(registry, first, second, seed))
+			.collect::<Vec<_>>();
+
+		Ok(TournamentResult::new(self.behaviors.clone(), games))
+	}
+
+	fn play(&self, registry: &BehaviorRegistry, first: usize, second: usize, seed: u64) -> Game {
+		let mut random_generator = SmallRng::seed_from_u64(seed);
+		let behaviors = [first, second]
+			.iter()
+			.flat_map(|&index| std::iter::repeat_n(&self.behaviors[index], self.agents_per_behavior))
+			.map(|name| registry.create(name).expect("Behaviors were validated before"))
+			.collect();
+		let mut world = World::random_with_behaviors(self.bounds, behaviors, false, &mut random_generator);
+
+		let game_mode = Arc::new(ClassicTag {
+			tag_limit: self.tag_limit,
+		});
+		let mut termination: Vec<Box<dyn TerminationCondition>> = vec![
+			Box::new(IterationLimit(self.iterations)),
+			Box::new(Victory(game_mode.clone())),
+		];
+		let reason = world.simulate_until(&mut termination);
+		let result = MatchResult::new(&world, game_mode.as_ref(), reason);
+
+		let (first_scores, second_scores) = result.scores.split_at(self.agents_per_behavior);
+		let first_score = first_scores.iter().sum::<f32>();
+		let second_score = second_scores.iter().sum::<f32>();
+		let outcome = if first_score > second_score {
+			1.0
+		} else if first_score < second_score {
+			0.0
+		} else {
+			0.5
+		};
+
+		Game {
+			first,
+			second,
+			seed,
+			outcome,
+		}
+	}
+}
+
+/// Ratings of all behaviors that took part in a [`Tournament`]
+pub struct TournamentResult {
+	pub games: Vec<Game>,
+	/// Ratings, ranked from best to worst
+	pub ratings: Vec<Rating>,
+}
+
+/// Elo rating of a single behavior with a 95% confidence interval
+#[derive(Clone, Debug)]
+pub struct Rating {
+	pub behavior: String,
+	pub elo: f32,
+	pub lower: f32,
+	pub upper: f32,
+	pub wins: usize,
+	pub draws: usize,
+	pub losses: usize,
+}
+
+impl TournamentResult {
+	pub fn new(behaviors: Vec<String>, games: Vec<Game>) -> Self {
+		let elo = elo_ratings(behaviors.len(), &games);
+
+		// Elo depends on the order of the games, so resampling them also covers that uncertainty
+		let mut random_generator = SmallRng::seed_from_u64(0);
+		let mut samples = vec![Vec::with_capacity(Tournament::BOOTSTRAP_SAMPLES); behaviors.len()];
+		for _ in 0..Tournament::BOOTSTRAP_SAMPLES {
+			let resampled = (0..games.len())
+				.map(|_| games[random_generator.gen_range(0..games.len())])
+				.collect::<Vec<_>>();
+			for (behavior_samples, rating) in samples.iter_mut().zip(elo_ratings(behaviors.len(), &resampled)) {
+				behavior_samples.push(rating);
+			}
+		}
+
+		let mut ratings = behaviors
+			.into_iter()
+			.enumerate()
+			.zip(samples)
+			.map(|((index, behavior), mut behavior_samples)| {
+				behavior_samples.sort_by(|a, b| a.partial_cmp(b).expect("Invalid rating"));
+				let outcomes = games.iter().filter_map(|game| {
+					if game.first == index {
+						Some(game.outcome)
+					} else if game.second == index {
+						Some(1.0 - game.outcome)
+					} else {
+						None
+					}
+				});
+
+				let (mut wins, mut draws, mut losses) = (0, 0, 0);
+				for outcome in outcomes {
+					if outcome > 0.5 {
+						wins += 1;
+					} else if outcome < 0.5 {
+						losses += 1;
+					} else {
+						draws += 1;
+					}
+				}
+
+				Rating {
+					behavior,
+					elo: elo[index],
+					lower: percentile(&behavior_samples, 0.025),
+					upper: percentile(&behavior_samples, 0.975),
+					wins,
+					draws,
+					losses,
+				}
+			})
+			.collect::<Vec<_>>();
+
+		ratings.sort_by(|a, b| b.elo.partial_cmp(&a.elo).expect("Invalid rating"));
+		Self { games, ratings }
+	}
+}
+
+impl Display for TournamentResult {
+	fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+		writeln!(formatter, "Games: {}", self.games.len())?;
+		for (rank, rating) in self.ratings.iter().enumerate() {
+			writeln!(
+				formatter,
+				"{}. {}: Elo: {:.0} (95% CI: {:.0} - {:.0}), Wins: {}, Draws: {}, Losses: {}",
+				rank + 1,
+				rating.behavior,
+				rating.elo,
+				rating.lower,
+				rating.upper,
+				rating.wins,
+				rating.draws,
+				rating.losses,
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Calculate Elo ratings by going through the games in order
+fn elo_ratings(behavior_count: usize, games: &[Game]) -> Vec<f32> {
+	let mut ratings = vec![Tournament::INITIAL_RATING; behavior_count];
+	for game in games {
+		let expected = 1.0 / (1.0 + 10f32.powf((ratings[game.second] - ratings[game.first]) / 400.0));
+		let change = Tournament::K_FACTOR * (game.outcome - expected);
+		ratings[game.first] += change;
+		ratings[game.second] -= change;
+	}
+
+	ratings
+}
+
+fn percentile(sorted: &[f32], fraction: f32) -> f32 {
+	let index = ((sorted.len() - 1) as f32 * fraction).round() as usize;
+	sorted[index]
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_rate_winner_higher() {
+		let games = (0..20)
+			.map(|seed| Game {
+				first: 0,
+				second: 1,
+				seed,
+				outcome: 1.0,
+			})
+			.collect::<Vec<_>>();
+
+		let result = TournamentResult::new(vec!["winner".to_string(), "loser".to_string()], games);
+
+		let winner = &result.ratings[0];
+		let loser = &result.ratings[1];
+		assert_eq!("winner", winner.behavior);
+		assert_eq!(20, winner.wins);
+		assert_eq!(20, loser.losses);
+		assert!(winner.elo > Tournament::INITIAL_RATING);
+		assert!(loser.elo < Tournament::INITIAL_RATING);
+		assert!(winner.lower <= winner.elo && winner.elo <= winner.upper);
+		assert!((2.0 * Tournament::INITIAL_RATING - (winner.elo + loser.elo)).abs() < 0.01);
+	}
+
+	#[test]
+	fn should_reject_unknown_behaviors() {
+		let tournament = Tournament {
+			behaviors: vec!["default".to_string(), "unknown".to_string()],
+			seeds: vec![0],
+			agents_per_behavior: 1,
+			bounds: Vector::new(100.0, 100.0),
+			iterations: 10,
+			tag_limit: None,
+		};
+
+		assert!(tournament.run(&BehaviorRegistry::default()).is_err());
+	}
+
+	#[test]
+	fn should_reject_matches_without_agents() {
+		let tournament = Tournament {
+			behaviors: vec!["default".to_string(), "chasing".to_string()],
+			seeds: vec![0],
+			agents_per_behavior: 0,
+			bounds: Vector::new(100.0, 100.0),
+			iterations: 10,
+			tag_limit: None,
+		};
+
+		assert!(tournament.run(&BehaviorRegistry::default()).is_err());
+	}
+
+	#[test]
+	fn should_reject_empty_or_infinite_playing_fields() {
+		for bounds in [Vector::new(0.0, 100.0), Vector::new(100.0, f32::INFINITY)] {
+			let tournament = Tournament {
+				behaviors: vec!["default".to_string(), "chasing".to_string()],
+				seeds: vec![0],
+				agents_per_behavior: 1,
+				bounds,
+				iterations: 10,
+				tag_limit: None,
+			};
+
+			assert!(tournament.run(&BehaviorRegistry::default()).is_err());
+		}
+	}
+}
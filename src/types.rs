@@ -24,3 +24,38 @@ pub fn normalize_radians(angle: f32) -> f32 {
 		remainder
 	}
 }
+
+/// Like [`normalize_radians`], but the result is between `-pi` and `+pi`
+pub fn normalize_signed_radians(angle: f32) -> f32 {
+	let angle = normalize_radians(angle);
+	if angle > PI {
+		angle - 2.0 * PI
+	} else {
+		angle
+	}
+}
+
+/// Interpolates between two angles along the shorter way around the circle, `progress` is between 0 and 1
+pub fn interpolate_radians(from: Radians, to: Radians, progress: f32) -> Radians {
+	let difference = normalize_signed_radians(to - from);
+	normalize_radians(from + difference * progress)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_interpolate_the_shorter_way_around() {
+		let from = degrees_to_radians(350.0);
+		let to = degrees_to_radians(30.0);
+
+		assert_eq!(10.0, radians_to_degrees(interpolate_radians(from, to, 0.5)).round());
+		assert_eq!(350.0, radians_to_degrees(interpolate_radians(to, from, 1.0)).round());
+		assert_eq!(90.0, radians_to_degrees(interpolate_radians(0.0, PI, 0.5)).round());
+		assert_eq!(
+			radians_to_degrees(from).round(),
+			radians_to_degrees(interpolate_radians(from, to, 0.0)).round()
+		);
+	}
+}
@@ -1,14 +1,28 @@
 use crate::world::World;
 use static_assertions::assert_obj_safe;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 /// Interface for different viewer implementations
 /// A [`Viewer`] displays the state of a [`World`] and simulation progress.
+///
+/// [`Viewer::iteration`] and [`Viewer::finished`] are called from the simulation thread,
+/// [`Viewer::run`] is called from the main thread and may block until the viewer is closed.
 pub trait Viewer: Send + Sync + 'static {
 	fn iteration(&self, world: &World);
 	fn finished(&self, world: &World);
 	fn run(&self);
+
+	/// Called from the simulation thread with the initial state of every round, before its first step
+	fn round_started(&self, _world: &World) {}
+
+	/// Called from the simulation thread with the final state of every round, before the next one is started
+	fn round_finished(&self, _world: &World) {}
+
+	/// Does [`Viewer::run`] have to be called on the main thread? (e.g. because of winit)
+	fn requires_main_thread(&self) -> bool {
+		false
+	}
 }
 
 assert_obj_safe!(Viewer);
@@ -46,3 +60,152 @@ impl Viewer for CommandlineViewer {
 		// Nothing to do
 	}
 }
+
+/// Viewer that fans out to multiple viewers at once, e.g. a visualization and a recorder.
+///
+/// At most one of the viewers may require the main thread. On [`Viewer::run`], all other viewers
+/// are run on their own threads, while the one requiring the main thread runs on the calling thread.
+pub struct CompositeViewer {
+	viewers: Vec<Arc<dyn Viewer>>,
+}
+
+impl CompositeViewer {
+	pub fn new(viewers: Vec<Arc<dyn Viewer>>) -> Result<Self, String> {
+		let main_thread_viewers = viewers.iter().filter(|viewer| viewer.requires_main_thread()).count();
+		if main_thread_viewers > 1 {
+			return Err(format!(
+				"Only one viewer can run on the main thread, but {main_thread_viewers} require it"
+			));
+		}
+
+		Ok(Self { viewers })
+	}
+}
+
+impl Viewer for CompositeViewer {
+	fn iteration(&self, world: &World) {
+		for viewer in &self.viewers {
+			viewer.iteration(world);
+		}
+	}
+
+	fn finished(&self, world: &World) {
+		for viewer in &self.viewers {
+			viewer.finished(world);
+		}
+	}
+
+	fn round_started(&self, world: &World) {
+		for viewer in &self.viewers {
+			viewer.round_started(world);
+		}
+	}
+
+	fn round_finished(&self, world: &World) {
+		for viewer in &self.viewers {
+			viewer.round_finished(world);
+		}
+	}
+
+	fn run(&self) {
+		let (main_thread_viewers, other_viewers): (Vec<_>, Vec<_>) =
+			self.viewers.iter().partition(|viewer| viewer.requires_main_thread());
+
+		let handles = other_viewers
+			.into_iter()
+			.map(|viewer| {
+				let viewer = viewer.clone();
+				std::thread::spawn(move || viewer.run())
+			})
+			.collect::<Vec<_>>();
+
+		for viewer in main_thread_viewers {
+			viewer.run();
+		}
+
+		for handle in handles {
+			handle.join().expect("Viewer thread panicked");
+		}
+	}
+
+	fn requires_main_thread(&self) -> bool {
+		self.viewers.iter().any(|viewer| viewer.requires_main_thread())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::behavior::default::DefaultBehavior;
+	use crate::types::Vector;
+	use rand::rngs::SmallRng;
+	use rand::SeedableRng;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::thread::ThreadId;
+
+	#[derive(Default)]
+	struct RecordingViewer {
+		main_thread: bool,
+		iterations: AtomicUsize,
+		run_on: Mutex<Option<ThreadId>>,
+	}
+
+	impl Viewer for RecordingViewer {
+		fn iteration(&self, _world: &World) {
+			self.iterations.fetch_add(1, Ordering::SeqCst);
+		}
+
+		fn finished(&self, _world: &World) {}
+
+		fn run(&self) {
+			*self.run_on.lock().unwrap() = Some(std::thread::current().id());
+		}
+
+		fn requires_main_thread(&self) -> bool {
+			self.main_thread
+		}
+	}
+
+	#[test]
+	fn should_fan_out_to_all_viewers() {
+		let world = World::random(
+			Vector::new(10.0, 10.0),
+			2,
+			DefaultBehavior::default,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		);
+		let main_thread_viewer = Arc::new(RecordingViewer {
+			main_thread: true,
+			..Default::default()
+		});
+		let other_viewer = Arc::new(RecordingViewer::default());
+		let composite = CompositeViewer::new(vec![other_viewer.clone(), main_thread_viewer.clone()]).unwrap();
+
+		composite.iteration(&world);
+		composite.iteration(&world);
+		composite.run();
+
+		assert_eq!(2, main_thread_viewer.iterations.load(Ordering::SeqCst));
+		assert_eq!(2, other_viewer.iterations.load(Ordering::SeqCst));
+		assert!(composite.requires_main_thread());
+		let current_thread = Some(std::thread::current().id());
+		assert_eq!(current_thread, *main_thread_viewer.run_on.lock().unwrap());
+		assert!(other_viewer.run_on.lock().unwrap().is_some());
+		assert_ne!(current_thread, *other_viewer.run_on.lock().unwrap());
+	}
+
+	#[test]
+	fn should_reject_multiple_main_thread_viewers() {
+		let viewers = (0..2)
+			.map(|_| {
+				Arc::new(RecordingViewer {
+					main_thread: true,
+					..Default::default()
+				}) as Arc<dyn Viewer>
+			})
+			.collect();
+
+		assert!(CompositeViewer::new(viewers).is_err());
+	}
+}
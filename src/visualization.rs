@@ -1,42 +1,116 @@
 use crate::agent::Agent;
+use crate::behavior::human::HumanInput;
 use crate::id::Id;
-use crate::types::Vector;
+use crate::physics::Physics;
+use crate::runner::RunnerControl;
+use crate::types::{interpolate_radians, Vector};
 use crate::viewer::Viewer;
 use crate::world::{World, WorldSnapshot};
+use agent_mesh::BATCHED_RENDERING_THRESHOLD;
 use bevy::app::{EventReader, EventWriter};
 use bevy::asset::{AssetServer, Assets, Handle};
-use bevy::ecs::prelude::{Commands, IntoSystem, Query, Res};
+use bevy::ecs::prelude::{Commands, IntoSystem, Query, Res, With};
+use bevy::input::Input;
 use bevy::math::{Quat, Vec2, Vec3};
 use bevy::prelude::{
-	Color, OrthographicCameraBundle, ResMut, Sprite, SpriteBundle, Text, TextBundle, Transform, UiCameraBundle,
-	WindowDescriptor,
+	Color, KeyCode, Mesh, MouseButton, OrthographicCameraBundle, ResMut, Shader, Sprite, SpriteBundle, Text,
+	TextBundle, Texture, Time, Transform, UiCameraBundle, WindowDescriptor,
 };
+use bevy::render::pipeline::PipelineDescriptor;
 use bevy::sprite::ColorMaterial;
 use bevy::text::{TextSection, TextStyle};
-use bevy::ui::{AlignSelf, Style};
+use bevy::ui::{AlignSelf, Interaction, Style};
+use bevy::window::Windows;
 use bevy::DefaultPlugins;
+use camera::{CameraControl, MainCamera};
 use crossbeam::channel::{Receiver, Sender};
+use history::SnapshotHistory;
+use hud::{HudHistory, HudSample, HUD_WIDTH};
+use overlay::{Overlays, TrailHistory};
+use point_of_view::PointOfView;
+
+mod agent_mesh;
+mod camera;
+mod history;
+mod hud;
+mod overlay;
+mod point_of_view;
 
 /// Viewer implementation that does a graphical representation of the agents by using the bevy game engine
+///
+/// With a [`RunnerControl`], the simulation can be controlled with the keyboard: `space` pauses and resumes,
+/// `s`, `right` or `n` runs a single step and `+` and `-` change the speed.
+/// Clicking an agent selects it, `escape` clears the selection.
+///
+/// Worlds with many agents are drawn as a single mesh, without the per-agent debug overlays.
+///
+/// The window can be resized and the arena is scaled to fit. Dragging with the right or middle mouse button pans,
+/// the mouse wheel zooms, `c` keeps the current "it" in the center and `0` resets the view.
+///
+/// `v` shows the world from the point of view of the selected agent.
+/// Debug overlays are toggled with `f` (field of view), `r` (reach), `t` (target of "it") and `p` (movement trails).
+///
+/// A panel next to the playing field shows live statistics: "it", the time since the last tag, the tag rate,
+/// a leaderboard of the agents that spent the least time as "it" and charts of the recent history.
+///
+/// With a [`HumanInput`], the arrow keys or WASD steer the agents with a
+/// [`HumanBehavior`](crate::behavior::human::HumanBehavior), `shift` walks slowly and `enter` tags.
+/// Steps are then only run with `n`.
+///
+/// The most recent snapshots are kept, so the viewer can go back in time without affecting the simulation:
+/// `,` and `.` step through them (10 at a time with `shift`), `home` goes to the oldest and `end` back to the live
+/// one. Clicking or dragging on the timeline at the bottom jumps to that point in time.
 pub struct BevyViewer {
 	snapshot_sender: Sender<WorldSnapshot>,
 	snapshot_receiver: Receiver<WorldSnapshot>,
+	/// Unlike snapshots, samples are sent for every iteration, so the statistics are complete
+	sample_sender: Sender<HudSample>,
+	sample_receiver: Receiver<HudSample>,
 	bounds: Vector,
+	physics: Physics,
+	control: Option<RunnerControl>,
+	human_input: Option<HumanInput>,
 }
 
 impl BevyViewer {
 	pub fn new(bounds: Vector) -> Self {
 		let (snapshot_sender, snapshot_receiver) = crossbeam::channel::bounded(1);
+		let (sample_sender, sample_receiver) = crossbeam::channel::unbounded();
 		Self {
 			snapshot_sender,
 			snapshot_receiver,
+			sample_sender,
+			sample_receiver,
 			bounds,
+			physics: Physics::default(),
+			control: None,
+			human_input: None,
 		}
 	}
+
+	/// Draw the field of view and reach overlays for the given [`Physics`]
+	pub fn with_physics(mut self, physics: Physics) -> Self {
+		self.physics = physics;
+		self
+	}
+
+	/// Control the simulation with the keyboard
+	pub fn with_control(mut self, control: RunnerControl) -> Self {
+		self.control = Some(control);
+		self
+	}
+
+	/// Steer the agents with a [`HumanBehavior`](crate::behavior::human::HumanBehavior) with the keyboard
+	pub fn with_human_input(mut self, human_input: HumanInput) -> Self {
+		self.human_input = Some(human_input);
+		self
+	}
 }
 
 impl Viewer for BevyViewer {
 	fn iteration(&self, world: &World) {
+		// bevy may already be closed, the samples are only informational
+		let _ = self.sample_sender.send(HudSample::new(world));
 		if !self.snapshot_sender.is_full() {
 			// only snapshot if bevy is ready to draw a new frame
 			self.snapshot_sender
@@ -53,31 +127,96 @@ impl Viewer for BevyViewer {
 	}
 
 	fn run(&self) {
-		run_visualization(self.bounds, self.snapshot_receiver.clone())
+		run_visualization(
+			self.bounds,
+			self.physics,
+			self.snapshot_receiver.clone(),
+			self.sample_receiver.clone(),
+			self.control.clone(),
+			self.human_input.clone(),
+		)
+	}
+
+	fn requires_main_thread(&self) -> bool {
+		true
 	}
 }
 
-pub fn run_visualization(bounds: Vector, snapshot_receiver: crossbeam::channel::Receiver<WorldSnapshot>) {
+pub fn run_visualization(
+	bounds: Vector,
+	physics: Physics,
+	snapshot_receiver: crossbeam::channel::Receiver<WorldSnapshot>,
+	sample_receiver: crossbeam::channel::Receiver<HudSample>,
+	control: Option<RunnerControl>,
+	human_input: Option<HumanInput>,
+) {
 	let initial_snapshot = snapshot_receiver.recv().expect("Failed to get initial snapshot");
-	bevy::prelude::App::build()
+	let mut app = bevy::prelude::App::build();
+	if let Some(control) = control {
+		app.insert_resource(control);
+	}
+	if let Some(human_input) = human_input {
+		app.insert_resource(human_input);
+	}
+
+	app
 		// NOTE: The WindowDescriptor must be inserted BEFORE adding DefaultPlugins
 		.insert_resource(WindowDescriptor {
-			// The additional range is because a visual representation of an Agent has a width of Agent::RANGE pixels
-			width: (bounds.x + 3.0 * Agent::RANGE).round() as f32,
-			height: (bounds.y + 3.0 * Agent::RANGE).round() as f32,
+			// The additional space is because a visual representation of an Agent has a width of 2 * AGENT_HALF_SIZE
+			// Initially, the arena is shown at its actual size with the HUD to the right of it
+			width: (bounds.x + 3.0 * AGENT_HALF_SIZE + HUD_WIDTH).round(),
+			height: (bounds.y + 3.0 * AGENT_HALF_SIZE).round(),
 			title: "Simulation of a game of tag".to_string(),
 			vsync: true,
-			resizable: false,
+			resizable: true,
 			..Default::default()
 		})
 		.add_plugins(DefaultPlugins)
 		.add_event::<WorldSnapshot>()
 		.insert_resource(Bounds::from(bounds))
+		.insert_resource(physics)
+		.insert_resource(DisplayedState::from(&initial_snapshot))
+		.insert_resource(DisplayedAgents::new(&Bounds::from(bounds), &initial_snapshot))
+		.insert_resource(Interpolation::new(DisplayedAgents::new(
+			&Bounds::from(bounds),
+			&initial_snapshot,
+		)))
+		.insert_resource(Selection::default())
+		.insert_resource(Overlays::default())
+		.insert_resource(TrailHistory::default())
+		.insert_resource(CameraControl::default())
+		.insert_resource(PointOfView::default())
+		.insert_resource(SnapshotHistory::new(initial_snapshot.agents.len()))
+		.insert_resource(HudHistory::new(initial_snapshot.agents.len()))
+		.insert_resource(sample_receiver)
 		.insert_resource(initial_snapshot)
 		.insert_resource(snapshot_receiver)
 		.add_startup_system(setup.system())
+		.add_startup_system(hud::hud_setup_system.system())
+		.add_startup_system(agent_mesh_setup_system.system())
+		.add_startup_system(point_of_view::point_of_view_setup_system.system())
+		.add_startup_system(history::timeline_setup_system.system())
 		.add_system(world_update_event_system.system())
+		.add_system(history::history_control_system.system())
+		.add_system(history::timeline_system.system())
 		.add_system(agent_update_system.system())
+		.add_system(keyboard_control_system.system())
+		.add_system(human_input_system.system())
+		.add_system(selection_system.system())
+		.add_system(point_of_view::point_of_view_system.system())
+		.add_system(agent_highlight_system.system())
+		.add_system(agent_mesh::agent_mesh_system.system())
+		.add_system(status_text_system.system())
+		.add_system(overlay::overlay_toggle_system.system())
+		.add_system(overlay::field_of_view_overlay_system.system())
+		.add_system(overlay::reach_overlay_system.system())
+		.add_system(overlay::target_overlay_system.system())
+		.add_system(overlay::trail_overlay_system.system())
+		.add_system(camera::camera_control_system.system())
+		.add_system(camera::camera_update_system.system())
+		.add_system(hud::hud_sample_system.system())
+		.add_system(hud::hud_text_system.system())
+		.add_system(hud::chart_system.system())
 		.run();
 }
 
@@ -86,16 +225,118 @@ struct Bounds(Vec2);
 
 impl From<Vector> for Bounds {
 	fn from(vector: Vector) -> Self {
-		Self(Vec2::new(vector.x as f32, vector.y as f32))
+		Self(Vec2::new(vector.x, vector.y))
 	}
 }
 
+/// Iteration and roles of the [`WorldSnapshot`] that is currently displayed
+#[derive(Clone, Copy)]
+struct DisplayedState {
+	iteration: usize,
+	it: Id,
+	previous_it: Id,
+}
+
+impl From<&WorldSnapshot> for DisplayedState {
+	fn from(snapshot: &WorldSnapshot) -> Self {
+		Self {
+			iteration: snapshot.iteration,
+			it: snapshot.it,
+			previous_it: snapshot.previous_it,
+		}
+	}
+}
+
+/// Where the agents of the [`WorldSnapshot`] that is currently displayed are and what they do, indexed by [`Id`]
+#[derive(Clone)]
+struct DisplayedAgents {
+	translations: Vec<Vec3>,
+	headings: Vec<f32>,
+	targets: Vec<Option<Id>>,
+}
+
+impl DisplayedAgents {
+	fn new(bounds: &Bounds, snapshot: &WorldSnapshot) -> Self {
+		Self {
+			translations: snapshot
+				.agents
+				.iter()
+				.map(|agent| translation_for_agent(bounds, agent))
+				.collect(),
+			headings: snapshot.agents.iter().map(|agent| agent.heading).collect(),
+			targets: snapshot.targets.to_vec(),
+		}
+	}
+
+	/// Somewhere between two snapshots with the same agents, the targets are always the ones of `to`
+	fn interpolate(from: &Self, to: &Self, progress: f32) -> Self {
+		Self {
+			translations: from
+				.translations
+				.iter()
+				.zip(&to.translations)
+				.map(|(from, to)| from.lerp(*to, progress))
+				.collect(),
+			headings: from
+				.headings
+				.iter()
+				.zip(&to.headings)
+				.map(|(&from, &to)| interpolate_radians(from, to, progress))
+				.collect(),
+			targets: to.targets.clone(),
+		}
+	}
+}
+
+/// The displayed agents move smoothly from where they were when the latest snapshot arrived to where they are in it.
+/// The movement takes as long as it took for the latest snapshot to arrive, so it is finished about when the next one
+/// arrives. If snapshots arrive faster than frames are drawn, the ones in between are dropped.
+struct Interpolation {
+	from: DisplayedAgents,
+	to: DisplayedAgents,
+	/// When the latest snapshot arrived, in seconds since startup
+	start: f64,
+	duration: f64,
+	is_finished: bool,
+}
+
+impl Interpolation {
+	/// Longer pauses between snapshots, e.g. while single-stepping, aren't stretched out
+	const MAXIMUM_DURATION: f64 = 0.5;
+
+	fn new(agents: DisplayedAgents) -> Self {
+		Self {
+			from: agents.clone(),
+			to: agents,
+			start: 0.0,
+			duration: 0.0,
+			is_finished: true,
+		}
+	}
+}
+
+/// The agent that was selected by clicking on it
+#[derive(Default)]
+struct Selection(Option<Id>);
+
+/// Half the width of an agent on screen. This is only how it is drawn, the reach overlay shows the actual
+/// [`Physics::range`].
+const AGENT_HALF_SIZE: f32 = 10.0;
+
+/// Color of agents that aren't seen from the current [`PointOfView`]
+const HIDDEN_AGENT_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+
+/// Marks the status line at the top of the window
+struct StatusText;
+
 /// Type to combine the [`ColorMaterial`]s to be used for agents.
 /// This type is injected into `bevy` as a resource.
 struct AgentColors {
 	regular: Handle<ColorMaterial>,
 	it: Handle<ColorMaterial>,
 	previous_it: Handle<ColorMaterial>,
+	/// Agents that aren't seen from the current [`PointOfView`]
+	hidden: Handle<ColorMaterial>,
 }
 
 /// Sets up the entities and resource for the visualization.
@@ -103,44 +344,55 @@ fn setup(
 	mut commands: Commands,
 	initial_snapshot: Res<WorldSnapshot>,
 	mut materials: ResMut<Assets<ColorMaterial>>,
+	mut textures: ResMut<Assets<Texture>>,
 	asset_server: Res<AssetServer>,
 	bounds: Res<Bounds>,
+	physics: Res<Physics>,
 ) {
-	commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+	// the camera is moved and scaled to fit the arena by the camera systems
+	commands
+		.spawn_bundle(OrthographicCameraBundle::new_2d())
+		.insert(MainCamera);
 	commands.spawn_bundle(UiCameraBundle::default());
-	commands.spawn_bundle(TextBundle {
-		node: Default::default(),
-		style: Style {
-			align_self: AlignSelf::FlexEnd,
-			..Default::default()
-		},
-		text: Text {
-			sections: vec![TextSection {
-				value: format!("Iteration: {}", initial_snapshot.iteration),
-				style: TextStyle {
-					font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-					font_size: 20.0,
-					color: Color::WHITE,
-				},
-			}],
+	commands
+		.spawn_bundle(TextBundle {
+			node: Default::default(),
+			style: Style {
+				align_self: AlignSelf::FlexEnd,
+				..Default::default()
+			},
+			text: Text {
+				sections: vec![TextSection {
+					value: format!("Iteration: {}", initial_snapshot.iteration),
+					style: TextStyle {
+						font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+						font_size: 20.0,
+						color: Color::WHITE,
+					},
+				}],
+				..Default::default()
+			},
 			..Default::default()
-		},
-		..Default::default()
-	});
+		})
+		.insert(StatusText);
 
 	let color_materials = AgentColors {
 		regular: materials.add(Color::BLACK.into()),
 		it: materials.add(Color::RED.into()),
 		previous_it: materials.add(Color::GREEN.into()),
+		hidden: materials.add(HIDDEN_AGENT_COLOR.into()),
 	};
 	commands.insert_resource(color_materials);
 
-	for (index, agent) in initial_snapshot.agents.iter().enumerate() {
+	// large worlds are drawn by the agent mesh, without per-agent entities
+	let agent_count = initial_snapshot.agents.len();
+	let uses_sprites = agent_count < BATCHED_RENDERING_THRESHOLD;
+	for (index, agent) in initial_snapshot.agents.iter().enumerate().filter(|_| uses_sprites) {
 		let id = Id::from(index);
 		commands
 			.spawn_bundle(SpriteBundle {
 				sprite: Sprite {
-					size: Vec2::splat((Agent::RANGE * 2.0) as f32),
+					size: Vec2::splat(AGENT_HALF_SIZE * 2.0),
 					..Default::default()
 				},
 				transform: Transform {
@@ -153,14 +405,38 @@ fn setup(
 			.insert(id);
 	}
 
+	overlay::spawn_overlays(
+		&mut commands,
+		&mut materials,
+		&mut textures,
+		&physics,
+		// per-agent overlays would only clutter large worlds
+		if uses_sprites { agent_count } else { 0 },
+	);
+
 	// initialization is done, initial snapshot isn't required anymore
 	commands.remove_resource::<WorldSnapshot>();
 }
 
-/// Checks every frame if a new [`WorldSnapshot`] is available to be displayed
-/// and if so, sends it out as an event.
+/// Sets up the mesh that draws all agents of large worlds at once
+fn agent_mesh_setup_system(
+	mut commands: Commands,
+	initial_snapshot: Res<WorldSnapshot>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+	mut shaders: ResMut<Assets<Shader>>,
+) {
+	let agent_count = initial_snapshot.agents.len();
+	if agent_count >= BATCHED_RENDERING_THRESHOLD {
+		agent_mesh::spawn_agent_mesh(&mut commands, &mut meshes, &mut pipelines, &mut shaders, agent_count);
+	}
+}
+
+/// Checks every frame if a new [`WorldSnapshot`] is available, records it in the [`SnapshotHistory`]
+/// and, unless the viewer is rewound, sends it out as an event.
 fn world_update_event_system(
 	receiver: Res<crossbeam::channel::Receiver<WorldSnapshot>>,
+	mut history: ResMut<SnapshotHistory>,
 	mut event_writer: EventWriter<WorldSnapshot>,
 ) {
 	let latest_snapshot = match receiver.try_iter().last() {
@@ -168,44 +444,245 @@ fn world_update_event_system(
 		None => return,
 	};
 
-	event_writer.send(latest_snapshot);
+	if history.record(latest_snapshot.clone()) {
+		event_writer.send(latest_snapshot);
+	}
 }
 
-/// On every new [`WorldSnapshot`] event, updates the entities visualizing the
-/// agents and updates the text which displays the current iteration.
+/// On every new [`WorldSnapshot`] event, starts moving the entities visualizing the agents towards it,
+/// then interpolates their positions every frame and remembers what is displayed for the other systems.
 fn agent_update_system(
 	mut event_reader: EventReader<WorldSnapshot>,
-	mut agent_query: Query<(&mut Transform, &mut Handle<ColorMaterial>, &Id)>,
-	mut text_query: Query<&mut Text>,
+	mut agent_query: Query<(&mut Transform, &Id)>,
+	mut displayed_state: ResMut<DisplayedState>,
+	mut displayed_agents: ResMut<DisplayedAgents>,
+	mut interpolation: ResMut<Interpolation>,
 	bounds: Res<Bounds>,
-	color_materials: Res<AgentColors>,
+	time: Res<Time>,
 ) {
-	let latest_snapshot = match event_reader.iter().last() {
-		Some(snapshot) => snapshot,
+	let now = time.seconds_since_startup();
+	if let Some(latest_snapshot) = event_reader.iter().last() {
+		let to = DisplayedAgents::new(&bounds, latest_snapshot);
+		// agents of a new round are placed somewhere else, they don't move there
+		let is_new_round = latest_snapshot.iteration < displayed_state.iteration;
+		let from = if is_new_round {
+			to.clone()
+		} else {
+			displayed_agents.clone()
+		};
+
+		*interpolation = Interpolation {
+			from,
+			to,
+			start: now,
+			duration: (now - interpolation.start).min(Interpolation::MAXIMUM_DURATION),
+			is_finished: false,
+		};
+		*displayed_state = DisplayedState::from(latest_snapshot);
+	}
+	if interpolation.is_finished {
+		return;
+	}
+
+	let progress = if interpolation.duration > 0.0 {
+		((now - interpolation.start) / interpolation.duration).min(1.0)
+	} else {
+		1.0
+	};
+	interpolation.is_finished = progress >= 1.0;
+	*displayed_agents = DisplayedAgents::interpolate(&interpolation.from, &interpolation.to, progress as f32);
+	for (mut transform, &id) in agent_query.iter_mut() {
+		transform.translation = displayed_agents.translations[id];
+		transform.rotation = Quat::from_rotation_z(displayed_agents.headings[id]);
+	}
+}
+
+/// Pauses, resumes, steps and changes the speed of the simulation on key presses
+fn keyboard_control_system(
+	keys: Res<Input<KeyCode>>,
+	control: Option<Res<RunnerControl>>,
+	human_input: Option<Res<HumanInput>>,
+) {
+	let control = match control {
+		Some(control) => control,
 		None => return,
 	};
+	let any_pressed = |codes: &[KeyCode]| codes.iter().any(|&code| keys.just_pressed(code));
+	// the movement keys are reserved for steering the human agent
+	let step_keys: &[KeyCode] = if human_input.is_some() {
+		&[KeyCode::N]
+	} else {
+		&[KeyCode::S, KeyCode::Right, KeyCode::N]
+	};
 
-	for mut text in text_query.iter_mut() {
-		text.sections[0].value = format!("Iteration: {}", latest_snapshot.iteration);
+	if keys.just_pressed(KeyCode::Space) {
+		control.toggle_pause();
+	}
+	if any_pressed(step_keys) {
+		control.pause();
+		control.step();
+	}
+	if any_pressed(&[KeyCode::Plus, KeyCode::Equals, KeyCode::NumpadAdd]) {
+		control.speed_up();
+	}
+	if any_pressed(&[KeyCode::Minus, KeyCode::NumpadSubtract]) {
+		control.slow_down();
+	}
+}
+
+/// Steers the human agent with the arrow keys or WASD, `shift` walks slowly and `enter` tags
+fn human_input_system(keys: Res<Input<KeyCode>>, human_input: Option<Res<HumanInput>>) {
+	let human_input = match human_input {
+		Some(human_input) => human_input,
+		None => return,
+	};
+	let any_pressed = |codes: &[KeyCode]| codes.iter().any(|&code| keys.pressed(code));
+	let axis = |negative: &[KeyCode], positive: &[KeyCode]| {
+		f32::from(u8::from(any_pressed(positive))) - f32::from(u8::from(any_pressed(negative)))
+	};
+
+	let direction = Vec2::new(
+		axis(&[KeyCode::Left, KeyCode::A], &[KeyCode::Right, KeyCode::D]),
+		axis(&[KeyCode::Down, KeyCode::S], &[KeyCode::Up, KeyCode::W]),
+	);
+	let heading = (direction != Vec2::ZERO).then(|| direction.y.atan2(direction.x));
+	let speed = if any_pressed(&[KeyCode::LShift, KeyCode::RShift]) {
+		0.5
+	} else {
+		1.0
+	};
+	human_input.set_movement(heading, speed);
+
+	if keys.just_pressed(KeyCode::Return) || keys.just_pressed(KeyCode::NumpadEnter) {
+		human_input.request_tag();
 	}
+}
 
-	for (mut transform, mut material, &id) in agent_query.iter_mut() {
-		let agent = &latest_snapshot.agents[id];
-		transform.translation = translation_for_agent(&bounds, agent);
-		transform.rotation = Quat::from_rotation_z(agent.heading);
+/// Selects the agent closest to the mouse cursor on click
+fn selection_system(
+	mouse_buttons: Res<Input<MouseButton>>,
+	keys: Res<Input<KeyCode>>,
+	windows: Res<Windows>,
+	camera_query: Query<&Transform, With<MainCamera>>,
+	displayed_agents: Res<DisplayedAgents>,
+	interaction_query: Query<&Interaction>,
+	mut selection: ResMut<Selection>,
+) {
+	if keys.just_pressed(KeyCode::Escape) && selection.0.is_some() {
+		selection.0 = None;
+	}
+	if !mouse_buttons.just_pressed(MouseButton::Left) {
+		return;
+	}
+	// clicks on the user interface, e.g. the timeline, don't change the selection
+	if interaction_query
+		.iter()
+		.any(|interaction| *interaction == Interaction::Clicked)
+	{
+		return;
+	}
+
+	let window = match windows.get_primary() {
+		Some(window) => window,
+		None => return,
+	};
+	let cursor = match window.cursor_position() {
+		Some(cursor) => cursor,
+		None => return,
+	};
+	let camera = match camera_query.iter().next() {
+		Some(camera) => camera,
+		None => return,
+	};
+	let position = camera::cursor_to_world(window, camera, cursor);
+
+	selection.0 = displayed_agents
+		.translations
+		.iter()
+		.enumerate()
+		.map(|(index, translation)| (translation.truncate().distance(position), Id::from(index)))
+		.filter(|&(distance, _)| distance <= AGENT_HALF_SIZE * 2.0)
+		.min_by(|(distance, _), (other_distance, _)| distance.total_cmp(other_distance))
+		.map(|(_, id)| id);
+}
 
-		if id == latest_snapshot.it {
+/// Colors the agents by their role and enlarges the selected one
+fn agent_highlight_system(
+	displayed_state: Res<DisplayedState>,
+	selection: Res<Selection>,
+	point_of_view: Res<PointOfView>,
+	color_materials: Res<AgentColors>,
+	mut agent_query: Query<(&mut Transform, &mut Handle<ColorMaterial>, &Id)>,
+) {
+	if !(displayed_state.is_changed() || selection.is_changed() || point_of_view.is_changed()) {
+		return;
+	}
+
+	for (mut transform, mut material, &id) in agent_query.iter_mut() {
+		if point_of_view.is_hidden(id) {
+			*material = color_materials.hidden.clone();
+		} else if id == displayed_state.it {
 			*material = color_materials.it.clone();
-		} else if id == latest_snapshot.previous_it {
+		} else if id == displayed_state.previous_it {
 			*material = color_materials.previous_it.clone();
 		} else {
 			*material = color_materials.regular.clone();
 		}
+
+		transform.scale = if selection.0 == Some(id) {
+			Vec3::splat(1.5)
+		} else {
+			Vec3::ONE
+		};
+	}
+}
+
+/// Shows the iteration, the speed of the simulation and the selected agent
+fn status_text_system(
+	displayed_state: Res<DisplayedState>,
+	selection: Res<Selection>,
+	point_of_view: Res<PointOfView>,
+	history: Res<SnapshotHistory>,
+	control: Option<Res<RunnerControl>>,
+	human_input: Option<Res<HumanInput>>,
+	mut text_query: Query<&mut Text, With<StatusText>>,
+) {
+	let mut status = format!("Iteration: {}", displayed_state.iteration);
+	if let Some(control) = control {
+		if control.is_paused() {
+			status += " | paused";
+		} else if control.ticks_per_second() > 0.0 {
+			status += &format!(" | {:.0} steps/s", control.ticks_per_second());
+		}
+	}
+	if let Some(id) = selection.0 {
+		status += &format!(" | Selected: {id}");
+		if id == displayed_state.it {
+			status += " (it)";
+		} else if id == displayed_state.previous_it {
+			status += " (previous it)";
+		}
+	}
+	if let Some(id) = human_input.and_then(|human_input| human_input.agent()) {
+		status += &format!(" | You: {id}");
+	}
+	if let Some(id) = point_of_view.agent() {
+		status += &format!(" | Point of view: {id}");
+	}
+	if let Some(steps) = history.steps_behind() {
+		status += &format!(" | Rewound {steps} steps (end: live)");
+	}
+
+	for mut text in text_query.iter_mut() {
+		if text.sections[0].value != status {
+			text.sections[0].value = status.clone();
+		}
 	}
 }
 
 fn translation_for_agent(bounds: &Bounds, agent: &Agent) -> Vec3 {
-	let inital_translation = Vec2::new(agent.position.x as f32, agent.position.y as f32).extend(0.0);
-	let adjustment = (bounds.0 / 2.0).extend(0.0); // because in bevy, (0, 0) is in the middle of the screen
-	inital_translation - adjustment
+	// because in bevy, (0, 0) is in the middle of the screen
+	let translation = Vec2::new(agent.position.x, agent.position.y) - bounds.0 / 2.0;
+	// agents are drawn above the overlays
+	translation.extend(1.0)
 }
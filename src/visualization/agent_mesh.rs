@@ -0,0 +1,212 @@
+use super::point_of_view::PointOfView;
+use super::{DisplayedAgents, DisplayedState, Selection, AGENT_HALF_SIZE, HIDDEN_AGENT_COLOR};
+use crate::id::Id;
+use bevy::asset::{Assets, Handle};
+use bevy::ecs::prelude::{Commands, Res};
+use bevy::math::{Quat, Vec2};
+use bevy::prelude::{Color, Mesh, MeshBundle, ResMut, Shader, Transform, Visible};
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::pipeline::{PipelineDescriptor, PrimitiveTopology, RenderPipeline, RenderPipelines};
+use bevy::render::shader::{ShaderStage, ShaderStages};
+
+/// Starting at this many agents, they are drawn by [`AgentMesh`] instead of one sprite per agent
+pub(super) const BATCHED_RENDERING_THRESHOLD: usize = 1000;
+
+/// All agents drawn as a single mesh with one square per agent, which is rebuilt from every displayed snapshot.
+/// One draw call for all agents keeps the frame rate interactive for worlds with 100k agents,
+/// where one entity per agent would spend most of the frame in bevy's per-entity systems.
+pub(super) struct AgentMesh(Handle<Mesh>);
+
+const COLOR_ATTRIBUTE: &str = "Vertex_Color";
+
+const VERTEX_SHADER: &str = r#"
+#version 450
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in vec4 Vertex_Color;
+layout(location = 0) out vec4 v_Color;
+
+layout(set = 0, binding = 0) uniform CameraViewProj {
+	mat4 ViewProj;
+};
+layout(set = 1, binding = 0) uniform Transform {
+	mat4 Model;
+};
+
+void main() {
+	gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
+	v_Color = Vertex_Color;
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+#version 450
+layout(location = 0) in vec4 v_Color;
+layout(location = 0) out vec4 o_Target;
+
+void main() {
+	o_Target = v_Color;
+}
+"#;
+
+/// Spawns the mesh for the given number of agents, its vertices are set by [`agent_mesh_system`]
+pub(super) fn spawn_agent_mesh(
+	commands: &mut Commands,
+	meshes: &mut Assets<Mesh>,
+	pipelines: &mut Assets<PipelineDescriptor>,
+	shaders: &mut Assets<Shader>,
+	agent_count: usize,
+) {
+	let pipeline = pipelines.add(PipelineDescriptor::default_config(ShaderStages {
+		vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, VERTEX_SHADER)),
+		fragment: Some(shaders.add(Shader::from_glsl(ShaderStage::Fragment, FRAGMENT_SHADER))),
+	}));
+
+	let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+	let indices = (0..agent_count as u32)
+		.flat_map(|agent| {
+			let first = agent * 4;
+			// counterclockwise, so the triangles aren't culled
+			[first, first + 1, first + 2, first, first + 2, first + 3]
+		})
+		.collect();
+	mesh.set_indices(Some(Indices::U32(indices)));
+	mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]; agent_count * 4]);
+	mesh.set_attribute(COLOR_ATTRIBUTE, vec![[0.0f32; 4]; agent_count * 4]);
+	let mesh = meshes.add(mesh);
+
+	commands.spawn_bundle(MeshBundle {
+		mesh: mesh.clone(),
+		render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(pipeline)]),
+		visible: Visible {
+			is_visible: true,
+			is_transparent: false,
+		},
+		transform: Transform::identity(),
+		..Default::default()
+	});
+	commands.insert_resource(AgentMesh(mesh));
+}
+
+/// Moves the squares of the agents in the mesh and colors them by their role
+pub(super) fn agent_mesh_system(
+	agent_mesh: Option<Res<AgentMesh>>,
+	displayed_state: Res<DisplayedState>,
+	displayed_agents: Res<DisplayedAgents>,
+	selection: Res<Selection>,
+	point_of_view: Res<PointOfView>,
+	mut meshes: ResMut<Assets<Mesh>>,
+) {
+	let agent_mesh = match agent_mesh {
+		Some(agent_mesh) => agent_mesh,
+		None => return,
+	};
+	if !(displayed_agents.is_changed() || selection.is_changed() || point_of_view.is_changed()) {
+		return;
+	}
+	let mesh = match meshes.get_mut(&agent_mesh.0) {
+		Some(mesh) => mesh,
+		None => return,
+	};
+
+	let (positions, colors) = agent_vertices(&displayed_state, &displayed_agents, &selection, &point_of_view);
+	mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+	mesh.set_attribute(COLOR_ATTRIBUTE, VertexAttributeValues::from(colors));
+}
+
+/// Corners of the rotated square of every agent, the same as its sprite would be, and their colors
+fn agent_vertices(
+	displayed_state: &DisplayedState,
+	displayed_agents: &DisplayedAgents,
+	selection: &Selection,
+	point_of_view: &PointOfView,
+) -> (Vec<[f32; 3]>, Vec<[f32; 4]>) {
+	let [regular, it, previous_it, hidden] =
+		[Color::BLACK, Color::RED, Color::GREEN, HIDDEN_AGENT_COLOR].map(|color| color.as_linear_rgba_f32());
+	let corners = [
+		Vec2::new(-1.0, -1.0),
+		Vec2::new(1.0, -1.0),
+		Vec2::new(1.0, 1.0),
+		Vec2::new(-1.0, 1.0),
+	];
+
+	let agent_count = displayed_agents.translations.len();
+	let mut positions = Vec::with_capacity(agent_count * 4);
+	let mut colors = Vec::with_capacity(agent_count * 4);
+	for (index, (&translation, &heading)) in displayed_agents
+		.translations
+		.iter()
+		.zip(&displayed_agents.headings)
+		.enumerate()
+	{
+		let id = Id::from(index);
+		let half_size = if selection.0 == Some(id) {
+			1.5 * AGENT_HALF_SIZE
+		} else {
+			AGENT_HALF_SIZE
+		};
+		let rotation = Quat::from_rotation_z(heading);
+		positions.extend(
+			corners
+				.iter()
+				.map(|&corner| <[f32; 3]>::from(translation + rotation * (corner * half_size).extend(0.0))),
+		);
+
+		let color = if point_of_view.is_hidden(id) {
+			hidden
+		} else if id == displayed_state.it {
+			it
+		} else if id == displayed_state.previous_it {
+			previous_it
+		} else {
+			regular
+		};
+		colors.extend_from_slice(&[color; 4]);
+	}
+
+	(positions, colors)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use bevy::math::Vec3;
+
+	#[test]
+	fn should_compile_shaders() {
+		for (stage, source) in [
+			(ShaderStage::Vertex, VERTEX_SHADER),
+			(ShaderStage::Fragment, FRAGMENT_SHADER),
+		] {
+			assert!(Shader::from_glsl(stage, source).get_spirv(None).is_ok());
+		}
+	}
+
+	#[test]
+	fn should_place_a_square_around_every_agent() {
+		let displayed_state = DisplayedState {
+			iteration: 1,
+			it: Id::from(1),
+			previous_it: Id::from(0),
+		};
+		let displayed_agents = DisplayedAgents {
+			translations: vec![Vec3::new(0.0, 0.0, 1.0), Vec3::new(10.0, 20.0, 1.0)],
+			headings: vec![0.0, std::f32::consts::FRAC_PI_2],
+			targets: vec![None, None],
+		};
+
+		let (positions, colors) = agent_vertices(
+			&displayed_state,
+			&displayed_agents,
+			&Selection(None),
+			&PointOfView::default(),
+		);
+
+		assert_eq!(8, positions.len());
+		assert_eq!([-AGENT_HALF_SIZE, -AGENT_HALF_SIZE, 1.0], positions[0]);
+		// rotated by 90°, the first corner is at the bottom right
+		let corner = Vec3::from(positions[4]);
+		assert!(corner.abs_diff_eq(Vec3::new(10.0 + AGENT_HALF_SIZE, 20.0 - AGENT_HALF_SIZE, 1.0), 1e-4));
+		assert_eq!(Color::GREEN.as_linear_rgba_f32(), colors[0]);
+		assert_eq!(Color::RED.as_linear_rgba_f32(), colors[7]);
+	}
+}
@@ -0,0 +1,144 @@
+use super::hud::HUD_WIDTH;
+use super::point_of_view::PointOfView;
+use super::{Bounds, DisplayedAgents, DisplayedState, AGENT_HALF_SIZE};
+use bevy::app::EventReader;
+use bevy::ecs::prelude::{Query, Res, With};
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::input::Input;
+use bevy::math::{Vec2, Vec3};
+use bevy::prelude::{KeyCode, MouseButton, ResMut, Transform};
+use bevy::window::{Window, Windows};
+
+/// Marks the camera showing the playing field
+pub(super) struct MainCamera;
+
+/// How the user moved the camera. Without any changes, the whole arena fits into the window.
+pub(super) struct CameraControl {
+	/// Relative to fitting the arena into the window, larger values zoom in
+	zoom: f32,
+	/// Center of the view relative to the center of the arena, in simulation units
+	pan: Vec2,
+	/// Keep the current "it" in the center of the view
+	follow_it: bool,
+}
+
+impl Default for CameraControl {
+	fn default() -> Self {
+		Self {
+			zoom: 1.0,
+			pan: Vec2::ZERO,
+			follow_it: false,
+		}
+	}
+}
+
+const MIN_ZOOM: f32 = 0.2;
+const MAX_ZOOM: f32 = 50.0;
+/// Zoom factor of one line on the mouse wheel
+const ZOOM_PER_LINE: f32 = 1.1;
+/// Touchpads scroll by pixels, this many of them count as one line
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Pans the view while the right or middle mouse button is held, zooms towards the cursor with the mouse wheel,
+/// toggles following "it" with `c` and resets the view with `0`
+pub(super) fn camera_control_system(
+	mut wheel_events: EventReader<MouseWheel>,
+	mut motion_events: EventReader<MouseMotion>,
+	mouse_buttons: Res<Input<MouseButton>>,
+	keys: Res<Input<KeyCode>>,
+	windows: Res<Windows>,
+	camera_query: Query<&Transform, With<MainCamera>>,
+	mut control: ResMut<CameraControl>,
+) {
+	if keys.just_pressed(KeyCode::C) {
+		control.follow_it = !control.follow_it;
+	}
+	if keys.just_pressed(KeyCode::Key0) || keys.just_pressed(KeyCode::Numpad0) {
+		*control = CameraControl::default();
+	}
+
+	let camera = match camera_query.iter().next() {
+		Some(camera) => camera,
+		None => return,
+	};
+	let scale = camera.scale.x;
+
+	if mouse_buttons.pressed(MouseButton::Right) || mouse_buttons.pressed(MouseButton::Middle) {
+		let delta = motion_events.iter().fold(Vec2::ZERO, |sum, event| sum + event.delta);
+		if delta != Vec2::ZERO {
+			// the mouse moves the arena, the y axis of the mouse points down
+			control.pan -= Vec2::new(delta.x, -delta.y) * scale;
+			control.follow_it = false;
+		}
+	}
+
+	let lines = wheel_events
+		.iter()
+		.map(|event| match event.unit {
+			MouseScrollUnit::Line => event.y,
+			MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+		})
+		.sum::<f32>();
+	if lines == 0.0 {
+		return;
+	}
+
+	let previous_zoom = control.zoom;
+	let zoom = (previous_zoom * ZOOM_PER_LINE.powf(lines)).clamp(MIN_ZOOM, MAX_ZOOM);
+	control.zoom = zoom;
+	// the point under the cursor stays in place, unless the view is centered on "it" anyway
+	let cursor = windows
+		.get_primary()
+		.and_then(|window| Some((window, window.cursor_position()?)));
+	if let (false, Some((window, cursor))) = (control.follow_it, cursor) {
+		// offset from the center of the view, which is shifted by the HUD
+		let offset_from_center = (cursor - window_center(window) + Vec2::new(HUD_WIDTH / 2.0, 0.0)) * scale;
+		control.pan += offset_from_center * (1.0 - previous_zoom / zoom);
+	}
+}
+
+/// Scales the arena to fit into the window, next to the HUD, and applies the zoom and pan of the user
+pub(super) fn camera_update_system(
+	windows: Res<Windows>,
+	bounds: Res<Bounds>,
+	mut control: ResMut<CameraControl>,
+	point_of_view: Res<PointOfView>,
+	displayed_state: Res<DisplayedState>,
+	displayed_agents: Res<DisplayedAgents>,
+	mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+	let window = match windows.get_primary() {
+		Some(window) => window,
+		None => return,
+	};
+
+	// The additional space is because a visual representation of an Agent has a width of 2 * AGENT_HALF_SIZE
+	let arena = bounds.0 + Vec2::splat(3.0 * AGENT_HALF_SIZE);
+	let available = Vec2::new((window.width() - HUD_WIDTH).max(1.0), window.height().max(1.0));
+	let fitting_scale = (arena / available).max_element();
+	let scale = fitting_scale / control.zoom;
+
+	// the view stays there when following is turned off
+	if let Some(agent) = point_of_view.agent() {
+		control.pan = displayed_agents.translations[agent].truncate();
+	} else if control.follow_it {
+		control.pan = displayed_agents.translations[displayed_state.it].truncate();
+	}
+	// the arena is centered in the part of the window that isn't covered by the HUD
+	let translation = control.pan + Vec2::new(HUD_WIDTH / 2.0 * scale, 0.0);
+
+	for mut transform in camera_query.iter_mut() {
+		transform.translation = translation.extend(transform.translation.z);
+		transform.scale = Vec3::new(scale, scale, 1.0);
+	}
+}
+
+/// Position of the given cursor position in the arena, which has its origin in the center of the arena
+pub(super) fn cursor_to_world(window: &Window, camera: &Transform, cursor: Vec2) -> Vec2 {
+	// the cursor position has its origin in the bottom left corner, the camera is in the middle of the window
+	camera.mul_vec3((cursor - window_center(window)).extend(0.0)).truncate()
+}
+
+fn window_center(window: &Window) -> Vec2 {
+	Vec2::new(window.width(), window.height()) / 2.0
+}
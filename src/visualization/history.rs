@@ -0,0 +1,266 @@
+use super::hud::HUD_WIDTH;
+use crate::world::WorldSnapshot;
+use bevy::app::EventWriter;
+use bevy::asset::Assets;
+use bevy::ecs::prelude::{Commands, Query, Res, With};
+use bevy::input::Input;
+use bevy::math::{Rect, Size};
+use bevy::prelude::{BuildChildren, Color, GlobalTransform, KeyCode, ResMut};
+use bevy::sprite::ColorMaterial;
+use bevy::ui::entity::NodeBundle;
+use bevy::ui::{Interaction, Node, PositionType, Style, Val};
+use bevy::window::Windows;
+use std::collections::VecDeque;
+
+/// The most recent displayed snapshots, so the viewer can go back in time without affecting the simulation.
+///
+/// While rewound, new snapshots are still recorded, but not displayed until the viewer goes live again.
+pub(super) struct SnapshotHistory {
+	snapshots: VecDeque<WorldSnapshot>,
+	capacity: usize,
+	/// Iteration that is displayed instead of the latest snapshot
+	rewound_to: Option<usize>,
+}
+
+/// The history is limited to this many agent states, so large worlds don't run out of memory
+const AGENT_STATE_BUDGET: usize = 1_000_000;
+const MINIMUM_CAPACITY: usize = 10;
+const MAXIMUM_CAPACITY: usize = 1000;
+/// Snapshots skipped with `shift` held
+const LARGE_STEP: isize = 10;
+
+impl SnapshotHistory {
+	pub(super) fn new(agent_count: usize) -> Self {
+		Self {
+			snapshots: VecDeque::new(),
+			capacity: (AGENT_STATE_BUDGET / agent_count.max(1)).clamp(MINIMUM_CAPACITY, MAXIMUM_CAPACITY),
+			rewound_to: None,
+		}
+	}
+
+	/// Remember the snapshot, returns whether it should be displayed
+	pub(super) fn record(&mut self, snapshot: WorldSnapshot) -> bool {
+		if let Some(last) = self.snapshots.back() {
+			// a new round started, the history of the previous one isn't continuous with it
+			if snapshot.iteration < last.iteration {
+				self.snapshots.clear();
+				self.rewound_to = None;
+			}
+		}
+		if self.snapshots.len() == self.capacity {
+			self.snapshots.pop_front();
+		}
+		self.snapshots.push_back(snapshot);
+
+		// the displayed snapshot might just have been dropped
+		if let (Some(iteration), Some(oldest)) = (self.rewound_to, self.snapshots.front()) {
+			if iteration < oldest.iteration {
+				self.rewound_to = Some(oldest.iteration);
+			}
+		}
+		self.rewound_to.is_none()
+	}
+
+	/// How many snapshots after the displayed one were recorded, `None` if the latest one is displayed
+	pub(super) fn steps_behind(&self) -> Option<usize> {
+		self.position().map(|position| self.snapshots.len() - 1 - position)
+	}
+
+	/// Index of the displayed snapshot, `None` if the latest one is displayed
+	fn position(&self) -> Option<usize> {
+		let iteration = self.rewound_to?;
+		Some(
+			self.snapshots
+				.partition_point(|snapshot| snapshot.iteration < iteration)
+				.min(self.snapshots.len().saturating_sub(1)),
+		)
+	}
+
+	/// Display the snapshot at the given index, going live at the latest one.
+	/// Returns the snapshot to display if it changed.
+	fn show(&mut self, position: usize) -> Option<WorldSnapshot> {
+		let last = self.snapshots.len().checked_sub(1)?;
+		let position = position.min(last);
+		let rewound_to = (position < last).then(|| self.snapshots[position].iteration);
+		if rewound_to == self.rewound_to {
+			return None;
+		}
+
+		self.rewound_to = rewound_to;
+		Some(self.snapshots[position].clone())
+	}
+
+	/// Move the given number of snapshots forwards or backwards in time
+	fn step(&mut self, steps: isize) -> Option<WorldSnapshot> {
+		let last = self.snapshots.len().checked_sub(1)?;
+		let position = self.position().unwrap_or(last);
+		self.show(position.saturating_add_signed(steps))
+	}
+
+	/// Display the snapshot at the given fraction (between 0 and 1) of the history
+	fn show_fraction(&mut self, fraction: f32) -> Option<WorldSnapshot> {
+		let last = self.snapshots.len().checked_sub(1)?;
+		self.show((fraction.clamp(0.0, 1.0) * last as f32).round() as usize)
+	}
+
+	/// Where the displayed snapshot is in the history, between 0 and 1
+	fn displayed_fraction(&self) -> f32 {
+		match (self.position(), self.snapshots.len()) {
+			(Some(position), length) if length > 1 => position as f32 / (length - 1) as f32,
+			_ => 1.0,
+		}
+	}
+}
+
+/// Track of the timeline slider, clicking or dragging on it shows the snapshot at that point in time
+pub(super) struct Timeline;
+
+pub(super) struct TimelineHandle;
+
+/// Spawns the timeline slider along the bottom of the playing field
+pub(super) fn timeline_setup_system(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+	let handle = commands
+		.spawn_bundle(NodeBundle {
+			style: Style {
+				position_type: PositionType::Absolute,
+				size: Size::new(Val::Px(4.0), Val::Px(16.0)),
+				position: Rect {
+					left: Val::Percent(100.0),
+					top: Val::Px(-4.0),
+					..Default::default()
+				},
+				..Default::default()
+			},
+			material: materials.add(Color::rgb(1.0, 0.8, 0.0).into()),
+			..Default::default()
+		})
+		.insert(TimelineHandle)
+		.id();
+
+	commands
+		.spawn_bundle(NodeBundle {
+			// the width follows from the distances to the edges, so it adapts to the size of the window
+			style: Style {
+				position_type: PositionType::Absolute,
+				size: Size::new(Val::Auto, Val::Px(8.0)),
+				position: Rect {
+					left: Val::Px(10.0),
+					right: Val::Px(HUD_WIDTH + 10.0),
+					// from the bottom in bevy 0.5
+					top: Val::Px(10.0),
+					..Default::default()
+				},
+				..Default::default()
+			},
+			material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.4).into()),
+			..Default::default()
+		})
+		.insert(Interaction::default())
+		.insert(Timeline)
+		.push_children(&[handle]);
+}
+
+/// Steps through the history with `,` and `.` (10 snapshots at a time with `shift`), `home` goes to the oldest
+/// snapshot, `end` back to the live one. Clicking or dragging on the timeline jumps to that point.
+pub(super) fn history_control_system(
+	keys: Res<Input<KeyCode>>,
+	windows: Res<Windows>,
+	timeline_query: Query<(&Interaction, &Node, &GlobalTransform), With<Timeline>>,
+	mut history: ResMut<SnapshotHistory>,
+	mut event_writer: EventWriter<WorldSnapshot>,
+) {
+	let step = if keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift) {
+		LARGE_STEP
+	} else {
+		1
+	};
+
+	let mut snapshot = None;
+	if keys.just_pressed(KeyCode::Comma) {
+		snapshot = history.step(-step);
+	}
+	if keys.just_pressed(KeyCode::Period) {
+		snapshot = history.step(step);
+	}
+	if keys.just_pressed(KeyCode::Home) {
+		snapshot = history.show(0);
+	}
+	if keys.just_pressed(KeyCode::End) {
+		snapshot = history.show(usize::MAX);
+	}
+
+	let cursor = windows.get_primary().and_then(|window| window.cursor_position());
+	for (interaction, node, transform) in timeline_query.iter() {
+		if let (Interaction::Clicked, Some(cursor)) = (interaction, cursor) {
+			// the transform of UI nodes is their center, in the same coordinates as the cursor
+			let left = transform.translation.x - node.size.x / 2.0;
+			snapshot = history.show_fraction((cursor.x - left) / node.size.x.max(1.0));
+		}
+	}
+
+	if let Some(snapshot) = snapshot {
+		event_writer.send(snapshot);
+	}
+}
+
+/// Moves the handle of the timeline to the displayed snapshot
+pub(super) fn timeline_system(
+	history: Res<SnapshotHistory>,
+	mut handle_query: Query<&mut Style, With<TimelineHandle>>,
+) {
+	if !history.is_changed() {
+		return;
+	}
+
+	for mut style in handle_query.iter_mut() {
+		style.position.left = Val::Percent(100.0 * history.displayed_fraction());
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::id::Id;
+
+	fn snapshot(iteration: usize) -> WorldSnapshot {
+		WorldSnapshot {
+			agents: Default::default(),
+			iteration,
+			it: Id::from(0),
+			previous_it: Id::from(0),
+			events: Vec::new(),
+			targets: Default::default(),
+		}
+	}
+
+	#[test]
+	fn should_rewind_without_missing_new_snapshots() {
+		let mut history = SnapshotHistory::new(usize::MAX);
+		assert_eq!(MINIMUM_CAPACITY, history.capacity);
+		for iteration in 1..=5 {
+			assert!(history.record(snapshot(iteration)));
+		}
+
+		assert_eq!(Some(3), history.step(-2).map(|snapshot| snapshot.iteration));
+		assert_eq!(Some(2), history.steps_behind());
+		// new snapshots are recorded, but not displayed while rewound
+		assert!(!history.record(snapshot(6)));
+		assert_eq!(Some(3), history.steps_behind());
+		assert_eq!(Some(1), history.show_fraction(0.0).map(|snapshot| snapshot.iteration));
+
+		// the oldest snapshots are dropped, even the displayed one
+		for iteration in 7..=20 {
+			history.record(snapshot(iteration));
+		}
+		assert_eq!(Some(11), history.rewound_to);
+
+		assert_eq!(Some(20), history.show(usize::MAX).map(|snapshot| snapshot.iteration));
+		assert_eq!(None, history.steps_behind());
+		assert!(history.step(1).is_none());
+		assert!(history.record(snapshot(21)));
+
+		// a new round starts a new history
+		assert!(history.record(snapshot(1)));
+		assert_eq!(1, history.snapshots.len());
+	}
+}
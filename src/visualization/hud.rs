@@ -0,0 +1,486 @@
+use crate::event::Event;
+use crate::id::Id;
+use crate::world::{World, WorldSnapshot};
+use bevy::app::EventReader;
+use bevy::asset::{AssetServer, Assets, Handle};
+use bevy::ecs::prelude::{Commands, Query, Res, With};
+use bevy::math::{Rect, Size};
+use bevy::prelude::{BuildChildren, Color, ResMut, Text, TextBundle};
+use bevy::sprite::ColorMaterial;
+use bevy::text::{Font, TextSection, TextStyle};
+use bevy::ui::entity::NodeBundle;
+use bevy::ui::{Display, PositionType, Style, Val};
+use crossbeam::channel::Receiver;
+use std::collections::VecDeque;
+
+/// Width of the panel to the right of the playing field
+pub(super) const HUD_WIDTH: f32 = 260.0;
+/// Tags are counted over this many iterations for the tag rate
+const TAG_RATE_WINDOW: usize = 100;
+const LEADERBOARD_LENGTH: usize = 10;
+/// Number of samples shown in a chart, one per iteration for the tag rate and one per displayed snapshot for the
+/// average distance
+const CHART_LENGTH: usize = 120;
+const CHART_WIDTH: f32 = 240.0;
+const CHART_HEIGHT: f32 = 60.0;
+/// Space for the title above a chart
+const CHART_TITLE_HEIGHT: f32 = 24.0;
+const MARGIN: f32 = 10.0;
+
+/// What happened in one step of the simulation, sent from the simulation thread to the HUD.
+/// Unlike snapshots, which are dropped while bevy is busy, every sample arrives, so no tag is missed.
+/// A sample only holds the events of the step, everything that depends on all agents is calculated from the snapshots
+/// on the side of the viewer, so the simulation isn't slowed down by it.
+pub struct HudSample {
+	iteration: usize,
+	it: Id,
+	events: Vec<Event>,
+}
+
+impl HudSample {
+	pub fn new(world: &World) -> Self {
+		Self {
+			iteration: world.iteration(),
+			it: world.it(),
+			events: world.events().to_vec(),
+		}
+	}
+}
+
+/// Statistics and chart history shown in the HUD, accumulated from [`HudSample`]s and displayed snapshots
+#[derive(Default)]
+pub(super) struct HudHistory {
+	iteration: Option<usize>,
+	it: Option<Id>,
+	last_tag_iteration: Option<usize>,
+	/// Iterations of the tags within the last [`TAG_RATE_WINDOW`] iterations
+	recent_tags: VecDeque<usize>,
+	agents: Vec<AgentStatistics>,
+	average_distances: VecDeque<f32>,
+	tag_rates: VecDeque<f32>,
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+struct AgentStatistics {
+	tags: usize,
+	times_tagged: usize,
+	iterations_as_it: usize,
+}
+
+impl HudHistory {
+	pub(super) fn new(agent_count: usize) -> Self {
+		Self {
+			agents: vec![AgentStatistics::default(); agent_count],
+			..Default::default()
+		}
+	}
+
+	fn record(&mut self, sample: &HudSample) {
+		if self.iteration >= Some(sample.iteration) {
+			// a new round started
+			*self = Self::new(self.agents.len());
+		}
+
+		// the tagger was "it" during the step, the tagged agent only afterwards
+		let mut it_during_step = sample.it;
+		for event in &sample.events {
+			let Event::Tag {
+				iteration,
+				tagger,
+				tagged,
+			} = *event;
+			it_during_step = tagger;
+			self.last_tag_iteration = Some(iteration);
+			self.recent_tags.push_back(iteration);
+			self.agents[usize::from(tagger)].tags += 1;
+			self.agents[usize::from(tagged)].times_tagged += 1;
+		}
+		self.agents[usize::from(it_during_step)].iterations_as_it += 1;
+
+		self.iteration = Some(sample.iteration);
+		self.it = Some(sample.it);
+		while let Some(&oldest) = self.recent_tags.front() {
+			if (oldest + TAG_RATE_WINDOW) > sample.iteration {
+				break;
+			}
+			self.recent_tags.pop_front();
+		}
+
+		let tag_rate = self.recent_tags.len() as f32;
+		push_sample(&mut self.tag_rates, tag_rate);
+	}
+
+	fn record_snapshot(&mut self, snapshot: &WorldSnapshot) {
+		if self.iteration < Some(snapshot.iteration) {
+			// the snapshot is from a round that is already over
+			return;
+		}
+
+		let agents = &snapshot.agents;
+		let it = &agents[usize::from(snapshot.it)];
+		let total_distance = agents.iter().map(|agent| agent.distance(it)).sum::<f32>();
+		// "it" has a distance of 0 to itself
+		push_sample(
+			&mut self.average_distances,
+			total_distance / (agents.len().max(2) - 1) as f32,
+		);
+	}
+
+	/// Agents that spent the least time as "it" first
+	fn leaderboard(&self) -> Vec<(Id, AgentStatistics)> {
+		let mut leaderboard = self
+			.agents
+			.iter()
+			.enumerate()
+			.map(|(index, statistics)| (Id::from(index), *statistics))
+			.collect::<Vec<_>>();
+		leaderboard.sort_by_key(|&(id, statistics)| {
+			(
+				statistics.iterations_as_it,
+				std::cmp::Reverse(statistics.tags),
+				usize::from(id),
+			)
+		});
+		leaderboard
+	}
+
+	fn text(&self) -> String {
+		let mut lines = vec![
+			match self.it {
+				Some(it) => format!("It: {it}"),
+				None => "It: -".to_string(),
+			},
+			match (self.iteration, self.last_tag_iteration) {
+				(Some(iteration), Some(last_tag_iteration)) => {
+					format!("Since last tag: {} steps", iteration - last_tag_iteration)
+				}
+				_ => "Since last tag: -".to_string(),
+			},
+			format!("Tags per {TAG_RATE_WINDOW} steps: {}", self.recent_tags.len()),
+			String::new(),
+			"Least time as it:".to_string(),
+		];
+		lines.extend(
+			self.leaderboard()
+				.iter()
+				.take(LEADERBOARD_LENGTH)
+				.enumerate()
+				.map(|(rank, (id, statistics))| {
+					format!(
+						"{:>2}. {id}: {} steps, {} tags, tagged {}x",
+						rank + 1,
+						statistics.iterations_as_it,
+						statistics.tags,
+						statistics.times_tagged
+					)
+				}),
+		);
+		lines.join("\n")
+	}
+}
+
+fn push_sample(samples: &mut VecDeque<f32>, sample: f32) {
+	if samples.len() == CHART_LENGTH {
+		samples.pop_front();
+	}
+	samples.push_back(sample);
+}
+
+pub(super) struct HudText;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum Chart {
+	AverageDistanceToIt,
+	TagRate,
+}
+
+impl Chart {
+	const ALL: [Chart; 2] = [Chart::AverageDistanceToIt, Chart::TagRate];
+
+	fn title(&self) -> String {
+		match self {
+			Chart::AverageDistanceToIt => "Average distance to it".to_string(),
+			Chart::TagRate => format!("Tags per {TAG_RATE_WINDOW} steps"),
+		}
+	}
+
+	fn samples<'history>(&self, history: &'history HudHistory) -> &'history VecDeque<f32> {
+		match self {
+			Chart::AverageDistanceToIt => &history.average_distances,
+			Chart::TagRate => &history.tag_rates,
+		}
+	}
+}
+
+pub(super) struct ChartTitle(Chart);
+
+/// One point of a line chart, the newest sample is on the right
+pub(super) struct ChartPoint {
+	chart: Chart,
+	/// How many samples ago
+	age: usize,
+	/// Bottom left corner of the chart in the panel
+	origin: (f32, f32),
+}
+
+/// Absolutely positioned node.
+/// In bevy 0.5, the y axis of the UI points up, so `top` is actually measured from the bottom of the window.
+fn absolute_style(left: f32, bottom: f32, size: Size<Val>) -> Style {
+	Style {
+		position_type: PositionType::Absolute,
+		position: Rect {
+			left: Val::Px(left),
+			top: Val::Px(bottom),
+			..Default::default()
+		},
+		size,
+		..Default::default()
+	}
+}
+
+fn text_bundle(value: String, style: Style, font: Handle<Font>) -> TextBundle {
+	TextBundle {
+		style,
+		text: Text {
+			sections: vec![TextSection {
+				value,
+				style: TextStyle {
+					font,
+					font_size: 14.0,
+					color: Color::WHITE,
+				},
+			}],
+			..Default::default()
+		},
+		..Default::default()
+	}
+}
+
+/// Spawns the HUD as a panel along the right edge of the window: Statistics at the top, charts at the bottom.
+/// Everything is positioned relative to the panel, so it stays in place when the window is resized.
+pub(super) fn hud_setup_system(
+	mut commands: Commands,
+	mut materials: ResMut<Assets<ColorMaterial>>,
+	asset_server: Res<AssetServer>,
+) {
+	let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+	let panel_style = Style {
+		position_type: PositionType::Absolute,
+		position: Rect {
+			right: Val::Px(0.0),
+			top: Val::Px(0.0),
+			..Default::default()
+		},
+		size: Size::new(Val::Px(HUD_WIDTH), Val::Percent(100.0)),
+		..Default::default()
+	};
+	let background = materials.add(Color::rgba(0.0, 0.0, 0.0, 0.3).into());
+	let line = materials.add(Color::rgb(1.0, 0.8, 0.0).into());
+
+	commands
+		.spawn_bundle(NodeBundle {
+			style: panel_style,
+			material: materials.add(Color::NONE.into()),
+			..Default::default()
+		})
+		.with_children(|panel| {
+			// the height of the text depends on its content, so it is placed from the top, which is `bottom` in bevy 0.5
+			let mut text_style = absolute_style(MARGIN, 0.0, Size::default());
+			text_style.position.top = Val::Undefined;
+			text_style.position.bottom = Val::Px(MARGIN + 20.0);
+			panel
+				.spawn_bundle(text_bundle(String::new(), text_style, font.clone()))
+				.insert(HudText);
+
+			for (index, &chart) in Chart::ALL.iter().enumerate() {
+				let bottom = MARGIN + index as f32 * (CHART_HEIGHT + CHART_TITLE_HEIGHT + MARGIN);
+				panel.spawn_bundle(NodeBundle {
+					style: absolute_style(MARGIN, bottom, Size::new(Val::Px(CHART_WIDTH), Val::Px(CHART_HEIGHT))),
+					material: background.clone(),
+					..Default::default()
+				});
+				panel
+					.spawn_bundle(text_bundle(
+						chart.title(),
+						absolute_style(MARGIN, bottom + CHART_HEIGHT + 4.0, Size::default()),
+						font.clone(),
+					))
+					.insert(ChartTitle(chart));
+
+				for age in 0..CHART_LENGTH {
+					let mut style = absolute_style(MARGIN, bottom, Size::new(Val::Px(2.0), Val::Px(2.0)));
+					style.display = Display::None;
+					panel
+						.spawn_bundle(NodeBundle {
+							style,
+							material: line.clone(),
+							..Default::default()
+						})
+						.insert(ChartPoint {
+							chart,
+							age,
+							origin: (MARGIN, bottom),
+						});
+				}
+			}
+		});
+}
+
+/// Accumulates all samples that arrived since the last frame, then the average distance of the displayed snapshot
+pub(super) fn hud_sample_system(
+	receiver: Res<Receiver<HudSample>>,
+	mut event_reader: EventReader<WorldSnapshot>,
+	mut history: ResMut<HudHistory>,
+) {
+	let mut samples = receiver.try_iter().peekable();
+	let latest_snapshot = event_reader.iter().last();
+	// don't mark the history as changed if nothing happened
+	if samples.peek().is_none() && latest_snapshot.is_none() {
+		return;
+	}
+
+	for sample in samples {
+		history.record(&sample);
+	}
+	if let Some(snapshot) = latest_snapshot {
+		history.record_snapshot(snapshot);
+	}
+}
+
+/// Shows the statistics and the current values of the charts
+pub(super) fn hud_text_system(
+	history: Res<HudHistory>,
+	mut text_query: Query<&mut Text, With<HudText>>,
+	mut title_query: Query<(&mut Text, &ChartTitle)>,
+) {
+	if !history.is_changed() {
+		return;
+	}
+
+	for mut text in text_query.iter_mut() {
+		text.sections[0].value = history.text();
+	}
+	for (mut text, &ChartTitle(chart)) in title_query.iter_mut() {
+		let current = chart.samples(&history).back().copied().unwrap_or_default();
+		text.sections[0].value = format!("{}: {current:.1}", chart.title());
+	}
+}
+
+/// Moves the points of the charts, every chart is scaled to its largest visible sample
+pub(super) fn chart_system(history: Res<HudHistory>, mut point_query: Query<(&mut Style, &ChartPoint)>) {
+	if !history.is_changed() {
+		return;
+	}
+
+	let maxima = Chart::ALL.map(|chart| chart.samples(&history).iter().copied().fold(1.0, f32::max));
+	for (mut style, point) in point_query.iter_mut() {
+		let samples = point.chart.samples(&history);
+		let sample = match samples.len().checked_sub(point.age + 1) {
+			Some(index) => samples[index],
+			None => {
+				style.display = Display::None;
+				continue;
+			}
+		};
+
+		let maximum = maxima[Chart::ALL.iter().position(|&chart| chart == point.chart).unwrap_or(0)];
+		let (left, bottom) = point.origin;
+		let step = CHART_WIDTH / CHART_LENGTH as f32;
+		style.display = Display::Flex;
+		style.position.left = Val::Px(left + CHART_WIDTH - (point.age + 1) as f32 * step);
+		style.position.top = Val::Px(bottom + (sample / maximum) * (CHART_HEIGHT - 2.0));
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::agent::Agent;
+	use crate::types::Vector;
+	use std::sync::Arc;
+
+	fn sample(iteration: usize, it: usize, events: Vec<Event>) -> HudSample {
+		HudSample {
+			iteration,
+			it: Id::from(it),
+			events,
+		}
+	}
+
+	#[test]
+	fn should_accumulate_tags_and_time_as_it() {
+		let mut statistics = HudHistory::new(3);
+		statistics.record(&sample(1, 0, Vec::new()));
+		statistics.record(&sample(
+			2,
+			1,
+			vec![Event::Tag {
+				iteration: 2,
+				tagger: Id::from(0),
+				tagged: Id::from(1),
+			}],
+		));
+		statistics.record(&sample(3, 1, Vec::new()));
+
+		assert_eq!(2, statistics.agents[0].iterations_as_it);
+		assert_eq!(1, statistics.agents[0].tags);
+		assert_eq!(1, statistics.agents[1].iterations_as_it);
+		assert_eq!(1, statistics.agents[1].times_tagged);
+		assert_eq!(
+			vec![Id::from(2), Id::from(1), Id::from(0)],
+			statistics.leaderboard().iter().map(|&(id, _)| id).collect::<Vec<_>>()
+		);
+		assert_eq!(
+			Some(1),
+			statistics
+				.iteration
+				.zip(statistics.last_tag_iteration)
+				.map(|(iteration, last_tag)| iteration - last_tag)
+		);
+		assert_eq!(
+			vec![0.0, 1.0, 1.0],
+			statistics.tag_rates.iter().copied().collect::<Vec<_>>()
+		);
+
+		// a new round starts from scratch
+		statistics.record(&sample(1, 2, Vec::new()));
+		assert_eq!(1, statistics.agents[2].iterations_as_it);
+		assert_eq!(0, statistics.agents[0].tags);
+		assert_eq!(1, statistics.tag_rates.len());
+	}
+
+	#[test]
+	fn should_chart_the_average_distance_of_snapshots() {
+		let snapshot = |iteration| WorldSnapshot {
+			agents: Arc::new(vec![
+				Agent {
+					position: Vector::new(0.0, 0.0),
+					heading: 0.0,
+				},
+				Agent {
+					position: Vector::new(3.0, 4.0),
+					heading: 0.0,
+				},
+				Agent {
+					position: Vector::new(0.0, 10.0),
+					heading: 0.0,
+				},
+			]),
+			iteration,
+			it: Id::from(0),
+			previous_it: Id::from(1),
+			events: Vec::new(),
+			targets: Default::default(),
+		};
+		let mut history = HudHistory::new(3);
+		history.record(&sample(1, 0, Vec::new()));
+		history.record(&sample(2, 0, Vec::new()));
+		history.record_snapshot(&snapshot(2));
+		assert_eq!(vec![7.5], history.average_distances.iter().copied().collect::<Vec<_>>());
+
+		// a new round started, the snapshot of the old one is outdated
+		history.record(&sample(1, 0, Vec::new()));
+		history.record_snapshot(&snapshot(2));
+		assert!(history.average_distances.is_empty());
+	}
+}
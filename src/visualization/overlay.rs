@@ -0,0 +1,277 @@
+use super::{DisplayedAgents, DisplayedState};
+use crate::id::Id;
+use crate::physics::Physics;
+use bevy::asset::{Assets, Handle};
+use bevy::ecs::prelude::{Commands, Query, Res};
+use bevy::input::Input;
+use bevy::math::{Quat, Vec2, Vec3};
+use bevy::prelude::{Color, KeyCode, ResMut, Sprite, SpriteBundle, Texture, Transform, Visible};
+use bevy::render::texture::{Extent3d, TextureDimension, TextureFormat};
+use bevy::sprite::{ColorMaterial, SpriteResizeMode};
+use std::collections::VecDeque;
+
+/// Which debug overlays are shown, every overlay is toggled by its own key.
+/// The overlays show why agents do what they do: Field of view cones, reach circles,
+/// a line from "it" to its target and fading movement trails.
+#[derive(Default)]
+pub(super) struct Overlays {
+	/// `F`
+	field_of_view: bool,
+	/// `R`
+	reach: bool,
+	/// `T`
+	target: bool,
+	/// `P` for "path"
+	trails: bool,
+}
+
+/// Positions of the agents in the last displayed snapshots, the newest one first
+#[derive(Default)]
+pub(super) struct TrailHistory {
+	positions: VecDeque<Vec<Vec3>>,
+	last_iteration: Option<usize>,
+}
+
+pub(super) struct FieldOfViewCone(Id);
+
+pub(super) struct ReachCircle(Id);
+
+pub(super) struct TargetLine;
+
+pub(super) struct TrailPoint {
+	agent: Id,
+	/// How many snapshots ago the agent was at this point
+	age: usize,
+}
+
+/// The field of view is unlimited, so the cones are only drawn up to this multiple of the range
+const CONE_LENGTH_IN_RANGES: f32 = 4.0;
+const TRAIL_LENGTH: usize = 20;
+/// Textures are rendered at a higher resolution than they are displayed, so they don't look blocky
+const TEXTURE_SCALE: f32 = 4.0;
+
+// Overlays are drawn below the agents (at z = 1), the camera sees everything above z = -0.1
+const TRAIL_Z: f32 = 0.1;
+const CONE_Z: f32 = 0.2;
+const REACH_Z: f32 = 0.3;
+const TARGET_Z: f32 = 0.4;
+
+/// Spawns the (initially invisible) overlay entities for the given number of agents
+pub(super) fn spawn_overlays(
+	commands: &mut Commands,
+	materials: &mut Assets<ColorMaterial>,
+	textures: &mut Assets<Texture>,
+	physics: &Physics,
+	agent_count: usize,
+) {
+	let cone_length = CONE_LENGTH_IN_RANGES * physics.range;
+	let cone_material = materials.add(ColorMaterial::texture(textures.add(cone_texture(
+		cone_length,
+		physics.field_of_view_angle,
+		[255, 204, 0, 50],
+	))));
+	let reach_material = materials.add(ColorMaterial::texture(
+		textures.add(ring_texture(physics.range, [255, 255, 255, 160])),
+	));
+	let trail_materials = (0..TRAIL_LENGTH)
+		.map(|age| {
+			let alpha = 0.6 * (1.0 - age as f32 / TRAIL_LENGTH as f32);
+			materials.add(Color::rgba(0.0, 0.0, 0.0, alpha).into())
+		})
+		.collect::<Vec<_>>();
+
+	for index in 0..agent_count {
+		let id = Id::from(index);
+		commands
+			.spawn_bundle(overlay_sprite(cone_material.clone(), Vec2::splat(2.0 * cone_length)))
+			.insert(FieldOfViewCone(id));
+		commands
+			.spawn_bundle(overlay_sprite(reach_material.clone(), Vec2::splat(2.0 * physics.range)))
+			.insert(ReachCircle(id));
+		for (age, material) in trail_materials.iter().enumerate() {
+			commands
+				.spawn_bundle(overlay_sprite(material.clone(), Vec2::splat(3.0)))
+				.insert(TrailPoint { agent: id, age });
+		}
+	}
+
+	let target_material = materials.add(Color::rgba(1.0, 0.8, 0.0, 0.9).into());
+	commands
+		.spawn_bundle(overlay_sprite(target_material, Vec2::ONE))
+		.insert(TargetLine);
+}
+
+fn overlay_sprite(material: Handle<ColorMaterial>, size: Vec2) -> SpriteBundle {
+	SpriteBundle {
+		sprite: Sprite {
+			size,
+			resize_mode: SpriteResizeMode::Manual,
+			..Default::default()
+		},
+		material,
+		visible: Visible {
+			is_visible: false,
+			is_transparent: true,
+		},
+		..Default::default()
+	}
+}
+
+pub(super) fn overlay_toggle_system(keys: Res<Input<KeyCode>>, mut overlays: ResMut<Overlays>) {
+	if keys.just_pressed(KeyCode::F) {
+		overlays.field_of_view = !overlays.field_of_view;
+	}
+	if keys.just_pressed(KeyCode::R) {
+		overlays.reach = !overlays.reach;
+	}
+	if keys.just_pressed(KeyCode::T) {
+		overlays.target = !overlays.target;
+	}
+	if keys.just_pressed(KeyCode::P) {
+		overlays.trails = !overlays.trails;
+	}
+}
+
+/// Moves and rotates the field of view cones along with the agents
+pub(super) fn field_of_view_overlay_system(
+	overlays: Res<Overlays>,
+	displayed_agents: Res<DisplayedAgents>,
+	mut cone_query: Query<(&mut Transform, &mut Visible, &FieldOfViewCone)>,
+) {
+	if !(overlays.is_changed() || displayed_agents.is_changed()) {
+		return;
+	}
+
+	for (mut transform, mut visible, &FieldOfViewCone(id)) in cone_query.iter_mut() {
+		visible.is_visible = overlays.field_of_view;
+		if overlays.field_of_view {
+			*transform = Transform {
+				translation: displayed_agents.translations[id].truncate().extend(CONE_Z),
+				rotation: Quat::from_rotation_z(displayed_agents.headings[id]),
+				..Default::default()
+			};
+		}
+	}
+}
+
+/// Moves the reach circles along with the agents
+pub(super) fn reach_overlay_system(
+	overlays: Res<Overlays>,
+	displayed_agents: Res<DisplayedAgents>,
+	mut reach_query: Query<(&mut Transform, &mut Visible, &ReachCircle)>,
+) {
+	if !(overlays.is_changed() || displayed_agents.is_changed()) {
+		return;
+	}
+
+	for (mut transform, mut visible, &ReachCircle(id)) in reach_query.iter_mut() {
+		visible.is_visible = overlays.reach;
+		if overlays.reach {
+			transform.translation = displayed_agents.translations[id].truncate().extend(REACH_Z);
+		}
+	}
+}
+
+/// Stretches the target line from "it" to the agent it goes after
+pub(super) fn target_overlay_system(
+	overlays: Res<Overlays>,
+	displayed_state: Res<DisplayedState>,
+	displayed_agents: Res<DisplayedAgents>,
+	mut line_query: Query<(&mut Transform, &mut Sprite, &mut Visible, &TargetLine)>,
+) {
+	if !(overlays.is_changed() || displayed_agents.is_changed()) {
+		return;
+	}
+
+	let it = displayed_state.it;
+	let target = displayed_agents.targets.get(usize::from(it)).copied().flatten();
+	for (mut transform, mut sprite, mut visible, _) in line_query.iter_mut() {
+		visible.is_visible = overlays.target && target.is_some();
+		let target = match target {
+			Some(target) if overlays.target => target,
+			_ => continue,
+		};
+
+		let start = displayed_agents.translations[it].truncate();
+		let end = displayed_agents.translations[target].truncate();
+		let difference = end - start;
+		sprite.size = Vec2::new(difference.length(), 2.0);
+		*transform = Transform {
+			translation: ((start + end) / 2.0).extend(TARGET_Z),
+			rotation: Quat::from_rotation_z(difference.y.atan2(difference.x)),
+			..Default::default()
+		};
+	}
+}
+
+/// Records the positions of the agents and moves the trail points there
+pub(super) fn trail_overlay_system(
+	overlays: Res<Overlays>,
+	displayed_state: Res<DisplayedState>,
+	displayed_agents: Res<DisplayedAgents>,
+	mut history: ResMut<TrailHistory>,
+	mut point_query: Query<(&mut Transform, &mut Visible, &TrailPoint)>,
+) {
+	if displayed_agents.is_changed() && (history.last_iteration != Some(displayed_state.iteration)) {
+		// a new round started, the agents were placed somewhere else
+		if history.last_iteration > Some(displayed_state.iteration) {
+			history.positions.clear();
+		}
+		history.last_iteration = Some(displayed_state.iteration);
+		history.positions.push_front(displayed_agents.translations.clone());
+		history.positions.truncate(TRAIL_LENGTH);
+	} else if !overlays.is_changed() {
+		return;
+	}
+
+	for (mut transform, mut visible, point) in point_query.iter_mut() {
+		let position = history.positions.get(point.age).map(|positions| positions[point.agent]);
+		visible.is_visible = overlays.trails && position.is_some();
+		if let (true, Some(position)) = (overlays.trails, position) {
+			transform.translation = position.truncate().extend(TRAIL_Z);
+		}
+	}
+}
+
+/// Texture of a circular sector around the x axis, centered in the texture
+fn cone_texture(length: f32, angle: f32, color: [u8; 4]) -> Texture {
+	let half_angle = angle / 2.0;
+	texture(
+		2.0 * length,
+		TEXTURE_SCALE,
+		|offset| (offset.length() <= length) && (offset.y.atan2(offset.x).abs() <= half_angle),
+		color,
+	)
+}
+
+/// Texture of a thin circle, centered in the texture
+fn ring_texture(radius: f32, color: [u8; 4]) -> Texture {
+	texture(
+		2.0 * radius + 2.0,
+		TEXTURE_SCALE,
+		|offset| (offset.length() - radius).abs() <= 0.5,
+		color,
+	)
+}
+
+/// Square texture of the given size in display units, `is_inside` gets the offset from the center in display units
+pub(super) fn texture(size: f32, pixels_per_unit: f32, is_inside: impl Fn(Vec2) -> bool, color: [u8; 4]) -> Texture {
+	let pixels = (size * pixels_per_unit).ceil() as u32;
+	let center = Vec2::splat(pixels as f32 / 2.0);
+	let mut data = Vec::with_capacity((pixels * pixels * 4) as usize);
+	for row in 0..pixels {
+		for column in 0..pixels {
+			let offset = (Vec2::new(column as f32 + 0.5, row as f32 + 0.5) - center) / pixels_per_unit;
+			// the texture is symmetric to the x axis, so the direction of y doesn't matter
+			let pixel = if is_inside(offset) { color } else { [0; 4] };
+			data.extend_from_slice(&pixel);
+		}
+	}
+
+	Texture::new(
+		Extent3d::new(pixels, pixels, 1),
+		TextureDimension::D2,
+		data,
+		TextureFormat::Rgba8UnormSrgb,
+	)
+}
@@ -0,0 +1,171 @@
+use super::overlay::texture;
+use super::{Bounds, DisplayedAgents, Selection, AGENT_HALF_SIZE};
+use crate::agent::Agent;
+use crate::id::Id;
+use crate::physics::Physics;
+use bevy::asset::Assets;
+use bevy::ecs::prelude::{Commands, Query, Res, With};
+use bevy::input::Input;
+use bevy::math::{Quat, Vec2};
+use bevy::prelude::{KeyCode, ResMut, Sprite, SpriteBundle, Texture, Transform, Visible};
+use bevy::sprite::{ColorMaterial, SpriteResizeMode};
+
+/// Shows the world from the perspective of one agent, toggled with `v` for the selected agent.
+///
+/// Agents that the viewing agent doesn't see are greyed out, by the same rules as
+/// [`WorldView::visible_agents`](crate::world::WorldView::visible_agents), and so is everything outside of its field
+/// of view.
+#[derive(Default)]
+pub(super) struct PointOfView {
+	agent: Option<Id>,
+	/// Which agents the viewing agent sees, indexed by [`Id`]
+	visible: Vec<bool>,
+}
+
+impl PointOfView {
+	/// The agent whose perspective is shown
+	pub(super) fn agent(&self) -> Option<Id> {
+		self.agent
+	}
+
+	/// Is the agent not seen from the current point of view? Without a point of view, every agent is seen.
+	pub(super) fn is_hidden(&self, id: Id) -> bool {
+		match self.agent {
+			Some(agent) => (agent != id) && !self.visible.get(usize::from(id)).copied().unwrap_or(false),
+			None => false,
+		}
+	}
+}
+
+/// Covers everything outside the field of view of the viewing agent
+pub(super) struct OutsideViewShade;
+
+/// The shade is drawn above the overlays (at most z = 0.4), but below the agents (at z = 1)
+const SHADE_Z: f32 = 0.5;
+/// The shade only needs sharp edges, not sharp details
+const SHADE_TEXTURE_PIXELS: f32 = 512.0;
+
+/// Spawns the (initially invisible) shade, which is large enough to cover the whole arena from any position
+pub(super) fn point_of_view_setup_system(
+	mut commands: Commands,
+	mut materials: ResMut<Assets<ColorMaterial>>,
+	mut textures: ResMut<Assets<Texture>>,
+	bounds: Res<Bounds>,
+	physics: Res<Physics>,
+) {
+	let size = 2.0 * (bounds.0.length() + 3.0 * AGENT_HALF_SIZE);
+	let half_angle = physics.field_of_view_angle / 2.0;
+	let shade = texture(
+		size,
+		SHADE_TEXTURE_PIXELS / size,
+		|offset| offset.y.atan2(offset.x).abs() > half_angle,
+		[128, 128, 128, 160],
+	);
+
+	commands
+		.spawn_bundle(SpriteBundle {
+			sprite: Sprite {
+				size: Vec2::splat(size),
+				resize_mode: SpriteResizeMode::Manual,
+				..Default::default()
+			},
+			material: materials.add(ColorMaterial::texture(textures.add(shade))),
+			visible: Visible {
+				is_visible: false,
+				is_transparent: true,
+			},
+			..Default::default()
+		})
+		.insert(OutsideViewShade);
+}
+
+/// Toggles the point of view with `v`, follows the selection while it is shown and updates what the agent sees
+pub(super) fn point_of_view_system(
+	keys: Res<Input<KeyCode>>,
+	selection: Res<Selection>,
+	physics: Res<Physics>,
+	displayed_agents: Res<DisplayedAgents>,
+	mut point_of_view: ResMut<PointOfView>,
+	mut shade_query: Query<(&mut Transform, &mut Visible), With<OutsideViewShade>>,
+) {
+	let mut agent = point_of_view.agent;
+	if keys.just_pressed(KeyCode::V) {
+		agent = match agent {
+			Some(_) => None,
+			None => selection.0,
+		};
+	} else if let (Some(_), Some(selected)) = (agent, selection.0) {
+		agent = Some(selected);
+	}
+
+	let is_unchanged = (agent == point_of_view.agent) && !displayed_agents.is_changed();
+	if is_unchanged || (agent.is_none() && point_of_view.agent.is_none()) {
+		return;
+	}
+
+	point_of_view.agent = agent;
+	point_of_view.visible = match agent {
+		Some(agent) => visible_agents(&physics, agent, &displayed_agents),
+		None => Vec::new(),
+	};
+	for (mut transform, mut visible) in shade_query.iter_mut() {
+		visible.is_visible = agent.is_some();
+		if let Some(agent) = agent {
+			*transform = Transform {
+				translation: displayed_agents.translations[agent].truncate().extend(SHADE_Z),
+				rotation: Quat::from_rotation_z(displayed_agents.headings[agent]),
+				..Default::default()
+			};
+		}
+	}
+}
+
+/// Which agents the given one sees, indexed by [`Id`]
+fn visible_agents(physics: &Physics, viewed_by: Id, displayed_agents: &DisplayedAgents) -> Vec<bool> {
+	// relations don't depend on where the origin is, so the translations can be used as positions
+	let agents = displayed_agents
+		.translations
+		.iter()
+		.zip(&displayed_agents.headings)
+		.map(|(translation, &heading)| Agent {
+			position: translation.truncate(),
+			heading,
+		})
+		.collect::<Vec<_>>();
+	let viewing_agent = &agents[viewed_by];
+
+	agents
+		.iter()
+		.enumerate()
+		.map(|(index, other)| (Id::from(index) != viewed_by) && physics.is_visible(&viewing_agent.relate_to(other)))
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use bevy::math::Vec3;
+	use std::f32::consts::PI;
+
+	#[test]
+	fn should_hide_agents_outside_of_the_field_of_view() {
+		let displayed_agents = DisplayedAgents {
+			translations: vec![
+				Vec3::new(0.0, 0.0, 1.0),
+				Vec3::new(50.0, 10.0, 1.0),
+				Vec3::new(-50.0, 0.0, 1.0),
+			],
+			headings: vec![0.0, PI, 0.0],
+			targets: vec![None; 3],
+		};
+		let point_of_view = PointOfView {
+			agent: Some(Id::from(0)),
+			visible: visible_agents(&Physics::default(), Id::from(0), &displayed_agents),
+		};
+
+		assert!(!point_of_view.is_hidden(Id::from(0)));
+		assert!(!point_of_view.is_hidden(Id::from(1)));
+		assert!(point_of_view.is_hidden(Id::from(2)));
+		assert!(!PointOfView::default().is_hidden(Id::from(2)));
+	}
+}
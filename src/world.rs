@@ -1,23 +1,54 @@
 use crate::agent::{Agent, AgentRelationShip};
-use crate::behavior::{Behavior, Operation};
+use crate::behavior::{Behavior, BoxedBehavior, Operation};
+use crate::event::Event;
+use crate::execution::{AdaptiveExecution, ExecutionStrategy};
 use crate::id::Id;
+use crate::layout::{AgentLayout, LazyColumns};
+use crate::physics::Physics;
+use crate::shard::ShardGrid;
+use crate::statistics::Statistics;
+use crate::termination::{TerminationCondition, TerminationReason};
 use crate::types::{radians_to_degrees, Vector};
 use rand::Rng;
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+use rayon::iter::{
+	IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator,
+	ParallelIterator,
+};
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 /// The world where the simulated agents live in and where all the simulation happens
 pub struct World {
 	iteration: usize,
-	agents: Vec<Agent>,
-	behaviors: Mutex<Vec<Box<dyn Behavior + Send + Sync + 'static>>>, // not strictly necessary to be a Mutex. But easier for now
+	/// How often the world was [reset](World::reset), starting at 0
+	round: usize,
+	/// Shared with the snapshots, so publishing a snapshot doesn't copy the agents
+	agents: Arc<Vec<Agent>>,
+	/// Who every agent went after in the last step, indexed by [`Id`]
+	targets: Arc<Vec<Option<Id>>>,
+	/// Buffers of the previous step, reused by the next one unless a snapshot still holds on to them
+	spare_agents: Arc<Vec<Agent>>,
+	spare_targets: Arc<Vec<Option<Id>>>,
+	/// The agents in a struct of arrays layout, only kept for [`AgentLayout::StructOfArrays`]
+	columns: Option<LazyColumns>,
+	behaviors: Mutex<Vec<BoxedBehavior>>, // not strictly necessary to be a Mutex. But easier for now
 	bounds: Vector,
+	physics: Physics,
 	it: Id,
 	previous_it: Id,
-	next_it: Mutex<Option<Id>>,
-	simulate_in_parallel: bool,
+	/// The next "it", together with the agent that tagged it
+	next_it: Mutex<Option<(Id, Id)>>,
+	execution: ExecutionStrategy,
+	/// Measurements for [`ExecutionStrategy::Automatic`]
+	adaptive_execution: Option<AdaptiveExecution>,
+	/// Simulate the agents region by region instead, see [`ShardGrid`]
+	shard_grid: Option<ShardGrid>,
+	statistics: Statistics,
+	/// Events of the last step
+	events: Vec<Event>,
 }
 
 impl World {
@@ -32,26 +63,130 @@ impl World {
 	where
 		BehaviorType: Behavior + Send + Sync + 'static,
 	{
-		let agents = (0..agent_count)
-			.into_iter()
-			.map(|_| Agent::random(bounds, random_generator))
+		let behaviors = (0..agent_count)
+			.map(|_| Box::new(behavior_constructor()) as BoxedBehavior)
 			.collect();
 
-		let behaviors = (0..agent_count)
-			.map(|_| Box::new(behavior_constructor()) as Box<dyn Behavior + Send + Sync + 'static>)
+		Self::random_with_behaviors(bounds, behaviors, simulate_in_parallel, random_generator)
+	}
+
+	/// Randomly generate one agent for every given behavior. This allows for mixed populations.
+	///
+	/// Panics if no behaviors are given, because there needs to be an agent that is "it".
+	pub fn random_with_behaviors(
+		bounds: Vector,
+		behaviors: Vec<BoxedBehavior>,
+		simulate_in_parallel: bool,
+		random_generator: &mut impl Rng,
+	) -> Self {
+		assert!(!behaviors.is_empty(), "A world needs at least one agent");
+		let agent_count = behaviors.len();
+		let agents = (0..agent_count)
+			.map(|_| Agent::random(bounds, random_generator))
 			.collect();
 
+		let targets = behaviors.iter().map(|behavior| behavior.target()).collect();
 		let it = random_generator.gen_range(0..agent_count).into();
 		Self {
 			iteration: Default::default(),
-			agents,
+			round: Default::default(),
+			agents: Arc::new(agents),
+			targets: Arc::new(targets),
+			spare_agents: Default::default(),
+			spare_targets: Default::default(),
+			columns: None,
 			behaviors: Mutex::new(behaviors),
 			bounds,
+			physics: Physics::default(),
 			it,
 			previous_it: it,
 			next_it: Default::default(),
-			simulate_in_parallel,
+			execution: if simulate_in_parallel {
+				ExecutionStrategy::Parallel
+			} else {
+				ExecutionStrategy::Sequential
+			},
+			adaptive_execution: None,
+			shard_grid: None,
+			statistics: Statistics::new(agent_count),
+			events: Vec::new(),
+		}
+	}
+
+	/// Use the given [`Physics`] instead of the default one
+	pub fn with_physics(mut self, physics: Physics) -> Self {
+		self.physics = physics;
+		self
+	}
+
+	/// Store the agents in the given [`AgentLayout`], the default is [`AgentLayout::ArrayOfStructs`]
+	pub fn with_layout(mut self, layout: AgentLayout) -> Self {
+		self.columns = match layout {
+			AgentLayout::ArrayOfStructs => None,
+			AgentLayout::StructOfArrays => Some(LazyColumns::default()),
+		};
+		self
+	}
+
+	/// Run the behaviors with the given [`ExecutionStrategy`] instead of the one given by `simulate_in_parallel`
+	pub fn with_execution_strategy(mut self, execution: ExecutionStrategy) -> Self {
+		self.set_execution_strategy(execution);
+		self
+	}
+
+	/// Run the behaviors with the given [`ExecutionStrategy`] from the next step on
+	pub fn set_execution_strategy(&mut self, execution: ExecutionStrategy) {
+		self.execution = execution;
+		self.adaptive_execution = (execution == ExecutionStrategy::Automatic)
+			.then(|| AdaptiveExecution::new(self.agents.len(), rayon::current_num_threads()));
+	}
+
+	/// How the behaviors are run
+	pub fn execution_strategy(&self) -> ExecutionStrategy {
+		self.execution
+	}
+
+	/// Simulate the agents in about the given number of regions of the arena in parallel, see [`ShardGrid`].
+	/// This takes precedence over the [`ExecutionStrategy`]. Only pays off with a limited
+	/// [`Physics::view_distance`], since every region gets all agents within it.
+	pub fn with_shards(mut self, shard_count: usize) -> Self {
+		self.shard_grid = Some(ShardGrid::new(self.bounds, shard_count));
+		self
+	}
+
+	/// Start over with the given agents and "it" in the next round.
+	/// The behaviors are kept, so any state they have carries over.
+	pub fn reset(&mut self, agents: Vec<Agent>, it: Id) {
+		assert_eq!(self.agents.len(), agents.len(), "Agent count must not change");
+
+		self.iteration = Default::default();
+		self.round += 1;
+		self.statistics = Statistics::new(agents.len());
+		self.agents = Arc::new(agents);
+		if let Some(columns) = &mut self.columns {
+			columns.invalidate();
 		}
+		self.targets = Arc::new(
+			self.behaviors
+				.lock()
+				.expect("Lock was poisoned")
+				.iter()
+				.map(|behavior| behavior.target())
+				.collect(),
+		);
+		self.it = it;
+		self.previous_it = it;
+		self.next_it = Default::default();
+		self.events.clear();
+	}
+
+	/// Start over with randomly placed agents and a random "it". The behaviors are kept.
+	pub fn randomize(&mut self, random_generator: &mut impl Rng) {
+		let agents = (0..self.agents.len())
+			.map(|_| Agent::random(self.bounds, random_generator))
+			.collect();
+		let it = random_generator.gen_range(0..self.agents.len()).into();
+		self.reset(agents, it);
 	}
 
 	/// Which iteration step the world is in
@@ -59,45 +194,217 @@ impl World {
 		self.iteration
 	}
 
+	/// Which round is played, starting at 0. Every [`World::reset`] starts a new round.
+	pub fn round(&self) -> usize {
+		self.round
+	}
+
+	/// Size of the playing field
+	pub fn bounds(&self) -> Vector {
+		self.bounds
+	}
+
+	/// Physical parameters that apply to all agents
+	pub fn physics(&self) -> &Physics {
+		&self.physics
+	}
+
+	/// How the agents are stored
+	pub fn layout(&self) -> AgentLayout {
+		match self.columns {
+			Some(_) => AgentLayout::StructOfArrays,
+			None => AgentLayout::ArrayOfStructs,
+		}
+	}
+
+	/// Number of agents in the world
+	pub fn agent_count(&self) -> usize {
+		self.agents.len()
+	}
+
+	/// All agents, indexed by [`Id`]
+	pub fn agents(&self) -> &[Agent] {
+		&self.agents
+	}
+
+	/// Names of the behaviors of all agents, indexed by [`Id`]
+	pub fn behavior_names(&self) -> Vec<&'static str> {
+		self.behaviors
+			.lock()
+			.expect("Lock was poisoned")
+			.iter()
+			.map(|behavior| behavior.name())
+			.collect()
+	}
+
+	/// [`Id`] of the agent that is "it"
+	pub fn it(&self) -> Id {
+		self.it
+	}
+
+	/// [`Id`] of the agent that was "it" previously
+	pub fn previous_it(&self) -> Id {
+		self.previous_it
+	}
+
+	/// Statistics collected since the simulation started
+	pub fn statistics(&self) -> &Statistics {
+		&self.statistics
+	}
+
+	/// Events that happened during the last step
+	pub fn events(&self) -> &[Event] {
+		&self.events
+	}
+
+	/// Run steps of the simulation until the given condition says it's time to stop
+	pub fn simulate_until(&mut self, termination: &mut dyn TerminationCondition) -> TerminationReason {
+		loop {
+			if let Some(reason) = termination.check(self) {
+				return reason;
+			}
+
+			self.simulate_step();
+		}
+	}
+
 	/// Run one single step of the simulation
 	pub fn simulate_step(&mut self) {
 		let mut behaviors_guard = self.behaviors.lock().expect("Lock was poisoned");
 
-		let next_agents = if self.simulate_in_parallel {
-			let behaviors = behaviors_guard.par_iter_mut();
-			let agents = self.agents.par_iter();
-
-			agents
-				.zip(behaviors)
-				.enumerate()
-				.map(|(index, (agent, behavior))| {
-					self.simulate_agent(Id::from(index), agent.clone(), behavior.as_mut())
-				})
-				.collect::<Vec<_>>()
-		} else {
+		let mut next_agents = take_buffer(&mut self.spare_agents);
+		let mut next_targets = take_buffer(&mut self.spare_targets);
+		let execution = match &self.adaptive_execution {
+			Some(adaptive_execution) => adaptive_execution.next(),
+			None => self.execution,
+		};
+		let start = Instant::now();
+		if let Some(shard_grid) = &self.shard_grid {
+			self.simulate_sharded(shard_grid, &mut behaviors_guard, &mut next_agents, &mut next_targets);
+		} else if execution == ExecutionStrategy::Sequential {
 			let behaviors = behaviors_guard.iter_mut();
 			let agents = self.agents.iter();
 
+			for (index, (agent, behavior)) in agents.zip(behaviors).enumerate() {
+				let world_view = self.world_view(Id::from(index), agent.clone());
+				let (agent, target) = self.simulate_agent(world_view, behavior.as_mut());
+				next_agents.push(agent);
+				next_targets.push(target);
+			}
+		} else {
+			let behaviors = behaviors_guard.par_iter_mut();
+			let agents = self.agents.par_iter();
+			let chunk_size = match execution {
+				ExecutionStrategy::Chunked(chunk_size) => chunk_size,
+				_ => 1,
+			};
+
 			agents
 				.zip(behaviors)
 				.enumerate()
+				.with_min_len(chunk_size)
 				.map(|(index, (agent, behavior))| {
-					self.simulate_agent(Id::from(index), agent.clone(), behavior.as_mut())
+					let world_view = self.world_view(Id::from(index), agent.clone());
+					self.simulate_agent(world_view, behavior.as_mut())
 				})
-				.collect::<Vec<_>>()
+				.unzip_into_vecs(&mut next_agents, &mut next_targets);
 		};
+		if let (Some(adaptive_execution), None) = (&mut self.adaptive_execution, &self.shard_grid) {
+			adaptive_execution.record(start.elapsed());
+		}
 
-		self.agents = next_agents;
-		if let Some(next_it) = self.next_it.lock().expect("Lock was poisoned").take() {
-			self.previous_it = self.it;
-			self.it = next_it;
+		self.spare_agents = std::mem::replace(&mut self.agents, Arc::new(next_agents));
+		self.spare_targets = std::mem::replace(&mut self.targets, Arc::new(next_targets));
+		if let Some(columns) = &mut self.columns {
+			columns.invalidate();
 		}
 		self.iteration += 1;
+		self.events.clear();
+		self.statistics.agents[self.it].iterations_as_it += 1;
+		if let Some((tagger, tagged)) = self.next_it.lock().expect("Lock was poisoned").take() {
+			self.previous_it = self.it;
+			self.it = tagged;
+
+			self.statistics.tag_count += 1;
+			self.statistics.last_tag_iteration = Some(self.iteration);
+			self.statistics.agents[tagger].tags += 1;
+			self.statistics.agents[tagged].times_tagged += 1;
+			self.events.push(Event::Tag {
+				iteration: self.iteration,
+				tagger,
+				tagged,
+			});
+		}
+
+		if self.it_sees_anybody() {
+			self.statistics.iterations_it_saw_nobody = 0;
+		} else {
+			self.statistics.iterations_it_saw_nobody += 1;
+		}
 	}
 
-	/// Simulate one single agent
-	fn simulate_agent(&self, id: Id, agent: Agent, behavior: &mut dyn Behavior) -> Agent {
-		let mut world_view = self.world_view(id, agent);
+	/// Can the agent that is "it" see any other agent?
+	/// Always checked one agent at a time, since it usually stops early and doesn't need to fill the columns.
+	fn it_sees_anybody(&self) -> bool {
+		let it_agent = &self.agents[self.it];
+		self.agents
+			.iter()
+			.enumerate()
+			.any(|(index, agent)| (Id::from(index) != self.it) && self.physics.is_visible(&it_agent.relate_to(agent)))
+	}
+
+	/// Simulate the agents region by region in parallel. Every region only knows the agents within the view distance of
+	/// it (its neighborhood), the tags between regions are resolved by the same rules as without sharding.
+	fn simulate_sharded(
+		&self,
+		shard_grid: &ShardGrid,
+		behaviors: &mut [BoxedBehavior],
+		next_agents: &mut Vec<Agent>,
+		next_targets: &mut Vec<Option<Id>>,
+	) {
+		let regions = shard_grid.partition(&self.agents, self.physics.view_distance);
+
+		// hand out the behavior of every agent to the region it is in
+		let mut behaviors = behaviors.iter_mut().map(Some).collect::<Vec<_>>();
+		let shards = regions
+			.iter()
+			.map(|region| {
+				region
+					.members
+					.iter()
+					.map(|&id| (id, behaviors[id].take().expect("Agent is in more than one region")))
+					.collect::<Vec<_>>()
+			})
+			.collect::<Vec<_>>();
+
+		let results = shards
+			.into_par_iter()
+			.zip(regions.par_iter())
+			.map(|(shard, region)| {
+				shard
+					.into_iter()
+					.map(|(id, behavior)| {
+						let world_view = self.world_view_in(id, self.agents[id].clone(), &region.neighborhood);
+						self.simulate_agent(world_view, behavior.as_mut())
+					})
+					.collect::<Vec<_>>()
+			})
+			.collect::<Vec<_>>();
+
+		// the members of every region are sorted by id, so the next result of the region of an agent is its own
+		let mut results = results.into_iter().map(Vec::into_iter).collect::<Vec<_>>();
+		for agent in self.agents.iter() {
+			let (next_agent, next_target) = results[shard_grid.region_index(agent.position)]
+				.next()
+				.expect("Every agent is simulated by its region");
+			next_agents.push(next_agent);
+			next_targets.push(next_target);
+		}
+	}
+
+	/// Simulate one single agent, returns where it moved and who it went after
+	fn simulate_agent(&self, mut world_view: WorldView, behavior: &mut dyn Behavior) -> (Agent, Option<Id>) {
+		let id = world_view.our_id();
 		let Operation {
 			direction,
 			velocity,
@@ -106,43 +413,88 @@ impl World {
 
 		// If the agent wants to tag someone, check if it is allowed and if so, store the next "it"
 		if let Some(tagged_id) = tag {
-			if world_view.reachable_agents().contains_key(&tagged_id) {
+			let is_reachable = world_view
+				.relation_to(tagged_id)
+				.is_some_and(|relationship| self.physics.is_reachable(&relationship));
+			if is_reachable {
 				let mut next_it = self.next_it.lock().expect("Lock was poisoned");
-				*next_it = Some(tagged_id);
+				if next_it.is_none_or(|(other_tagger, _)| self.tag_takes_precedence(id, other_tagger)) {
+					*next_it = Some((id, tagged_id));
+				}
 			}
 		}
 
-		world_view.agent.perform_movement(self.bounds, velocity, direction)
+		let agent = world_view
+			.agent
+			.perform_movement_with_physics(&self.physics, self.bounds, velocity, direction);
+		(agent, behavior.target())
+	}
+
+	/// Agents are simulated in any order, so if several of them tag someone in the same step, the tag of "it" wins,
+	/// otherwise the one of the agent with the lowest [`Id`]
+	fn tag_takes_precedence(&self, tagger: Id, other_tagger: Id) -> bool {
+		(tagger == self.it, Reverse(tagger)) > (other_tagger == self.it, Reverse(other_tagger))
 	}
 
-	fn world_view(&self, id: Id, agent: Agent) -> WorldView {
+	fn world_view(&self, id: Id, agent: Agent) -> WorldView<'_> {
 		WorldView {
 			world: self,
 			viewed_by: id,
 			agent,
+			neighborhood: None,
 			visible_agents: None,
 			reachable_agents: None,
 		}
 	}
 
+	/// View that only looks at the given agents, which have to include all agents within the view distance
+	fn world_view_in<'world>(&'world self, id: Id, agent: Agent, neighborhood: &'world [Id]) -> WorldView<'world> {
+		WorldView {
+			neighborhood: Some(neighborhood),
+			..self.world_view(id, agent)
+		}
+	}
+
 	/// Snapshots the world as it is right now.
+	///
+	/// The agents are shared with the world instead of copied, so this is cheap even for large worlds.
+	/// The world never modifies them, it writes the next step into a separate buffer.
 	pub fn snapshot(&self) -> WorldSnapshot {
-		// Optimization opportunity: Update existing snapshot instead of creating a new one
 		WorldSnapshot {
-			agents: self.agents.clone(),
+			agents: Arc::clone(&self.agents),
 			iteration: self.iteration,
 			it: self.it,
 			previous_it: self.previous_it,
+			events: self.events.clone(),
+			targets: Arc::clone(&self.targets),
 		}
 	}
 }
 
+/// Empty buffer for the next step, reusing the given one if nobody else holds on to it anymore.
+/// Otherwise, the new buffer has room for as many elements as the given one, so it doesn't grow while it is filled.
+fn take_buffer<Element>(buffer: &mut Arc<Vec<Element>>) -> Vec<Element> {
+	match Arc::try_unwrap(std::mem::take(buffer)) {
+		Ok(mut buffer) => {
+			buffer.clear();
+			buffer
+		}
+		Err(shared) => Vec::with_capacity(shared.len()),
+	}
+}
+
 /// A snapshot of a single iteration of the simulation.
+#[derive(Clone)]
 pub struct WorldSnapshot {
-	pub agents: Vec<Agent>,
+	/// Shared with the [`World`], cloning a snapshot is cheap
+	pub agents: Arc<Vec<Agent>>,
 	pub iteration: usize,
 	pub it: Id,
 	pub previous_it: Id,
+	/// Events of the step that led to this snapshot
+	pub events: Vec<Event>,
+	/// Who every agent went after, see [`Behavior::target`], indexed by [`Id`]
+	pub targets: Arc<Vec<Option<Id>>>,
 }
 
 impl Display for World {
@@ -175,6 +527,8 @@ pub struct WorldView<'world> {
 	world: &'world World,
 	viewed_by: Id,
 	agent: Agent,
+	/// All agents within the view distance of the viewing agent are in here (sorted by [`Id`]) if the world is sharded
+	neighborhood: Option<&'world [Id]>,
 	visible_agents: Option<HashMap<Id, AgentRelationShip>>,
 	reachable_agents: Option<HashMap<Id, AgentRelationShip>>,
 }
@@ -190,6 +544,11 @@ impl<'world> WorldView<'world> {
 		&self.agent
 	}
 
+	/// Physical parameters that apply to all agents
+	pub fn physics(&self) -> &Physics {
+		&self.world.physics
+	}
+
 	/// [`Id`] of the agent that is "it"
 	pub fn current_it(&self) -> Id {
 		self.world.it
@@ -202,40 +561,330 @@ impl<'world> WorldView<'world> {
 
 	/// Collects a collection of Agents that are visible from the perspective of the viewing Agent.
 	/// The data is collected only once and then cached.
+	///
+	/// Prefer the more targeted queries like [`WorldView::relation_to`] or [`WorldView::nearest_visible`] if only
+	/// some of the agents are of interest.
 	pub fn visible_agents(&mut self) -> &HashMap<Id, AgentRelationShip> {
-		if self.visible_agents.is_some() {
-			// FIXME: if let Some() would be better, but I can't get the borrow checker to agree with me here
-			return self.visible_agents.as_ref().unwrap();
+		match self.visible_agents {
+			Some(ref visible_agents) => visible_agents,
+			None => {
+				let visible_agents = self.visible().collect();
+				self.visible_agents.insert(visible_agents)
+			}
 		}
-
-		let visible_agents = self
-			.world
-			.agents
-			.iter()
-			.enumerate()
-			.map(|(other_id, other_agent)| (Id::from(other_id), self.agent.relate_to(other_agent)))
-			.filter(|(other_id, relationship)| (self.viewed_by != *other_id) && relationship.is_visible())
-			.collect();
-
-		self.visible_agents = Some(visible_agents);
-		self.visible_agents.as_ref().unwrap() // save because we just set it
 	}
 
 	/// Collects a collection of Agents that are reachable from the perspective of the viewing Agent.
 	/// The data is collected only once and then cached.
 	pub fn reachable_agents(&mut self) -> &HashMap<Id, AgentRelationShip> {
-		if self.reachable_agents.is_some() {
-			// FIXME: if let Some() would be better, but I can't get the borrow checker to agree with me here
-			return self.reachable_agents.as_ref().unwrap();
+		match self.reachable_agents {
+			Some(ref reachable_agents) => reachable_agents,
+			None => {
+				let physics = self.world.physics;
+				let reachable_agents = match &self.visible_agents {
+					Some(visible_agents) => visible_agents
+						.iter()
+						.filter(|(_, relationship)| physics.is_reachable(relationship))
+						.map(|(&id, relationship)| (id, relationship.clone()))
+						.collect(),
+					None => self.reachable().collect(),
+				};
+				self.reachable_agents.insert(reachable_agents)
+			}
 		}
+	}
 
-		let reachable_agents = self
-			.visible_agents()
-			.iter()
-			.filter(|(_, relationship)| relationship.is_reachable())
-			.map(|(&id, relationship)| (id, relationship.clone()))
-			.collect();
-		self.reachable_agents = Some(reachable_agents);
-		self.reachable_agents.as_ref().unwrap() // save because we just set it
+	/// Relationship to the given agent if it is visible from the perspective of the viewing Agent.
+	/// Unlike [`WorldView::visible_agents`], only this one relation is calculated.
+	pub fn relation_to(&self, id: Id) -> Option<AgentRelationShip> {
+		if id == self.viewed_by {
+			return None;
+		}
+
+		let relationship = self.agent.relate_to(self.world.agents.get(usize::from(id))?);
+		self.world.physics.is_visible(&relationship).then_some(relationship)
+	}
+
+	/// Iterates over the visible Agents in the order of their [`Id`]s, without collecting them.
+	pub fn visible(&self) -> Box<dyn Iterator<Item = (Id, AgentRelationShip)> + '_> {
+		let world = self.world;
+		let viewed_by = self.viewed_by;
+		let agent = &self.agent;
+		if let Some(neighborhood) = self.neighborhood {
+			return Box::new(
+				neighborhood
+					.iter()
+					.copied()
+					.filter(move |&other_id| other_id != viewed_by)
+					.map(move |other_id| (other_id, agent.relate_to(&world.agents[other_id])))
+					.filter(move |(_, relationship)| world.physics.is_visible(relationship)),
+			);
+		}
+		if let Some(columns) = &world.columns {
+			return Box::new(
+				columns
+					.get(&world.agents)
+					.visible_relationships(viewed_by, &world.physics),
+			);
+		}
+
+		Box::new(
+			world
+				.agents
+				.iter()
+				.enumerate()
+				.map(|(other_id, other_agent)| (Id::from(other_id), other_agent))
+				.filter(move |(other_id, _)| *other_id != viewed_by)
+				.map(move |(other_id, other_agent)| (other_id, agent.relate_to(other_agent)))
+				.filter(move |(_, relationship)| world.physics.is_visible(relationship)),
+		)
+	}
+
+	/// Iterates over the reachable Agents in the order of their [`Id`]s, without collecting them.
+	pub fn reachable(&self) -> Box<dyn Iterator<Item = (Id, AgentRelationShip)> + '_> {
+		let physics = self.world.physics;
+		Box::new(
+			self.visible()
+				.filter(move |(_, relationship)| physics.is_reachable(relationship)),
+		)
+	}
+
+	/// The nearest visible Agent that matches the given filter, the one with the lowest [`Id`] if several are
+	/// equally near.
+	pub fn nearest_visible(&self, filter: impl Fn(Id, &AgentRelationShip) -> bool) -> Option<(Id, AgentRelationShip)> {
+		self.visible()
+			.filter(|(id, relationship)| filter(*id, relationship))
+			.min_by(|(_, a), (_, b)| a.distance.total_cmp(&b.distance))
+	}
+
+	/// The (at most) `k` nearest visible Agents, sorted by distance
+	pub fn k_nearest(&self, k: usize) -> Vec<(Id, AgentRelationShip)> {
+		let mut nearest = self.visible().collect::<Vec<_>>();
+		let by_distance =
+			|(_, a): &(Id, AgentRelationShip), (_, b): &(Id, AgentRelationShip)| a.distance.total_cmp(&b.distance);
+		if nearest.len() > k {
+			if k == 0 {
+				return Vec::new();
+			}
+			nearest.select_nth_unstable_by(k - 1, by_distance);
+			nearest.truncate(k);
+		}
+		nearest.sort_by(by_distance);
+		nearest
+	}
+
+	/// Iterates over the visible Agents that are at most `radius` away, in the order of their [`Id`]s
+	pub fn within_radius(&self, radius: f32) -> impl Iterator<Item = (Id, AgentRelationShip)> + '_ {
+		self.visible()
+			.filter(move |(_, relationship)| relationship.distance <= radius)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::behavior::default::DefaultBehavior;
+	use rand::rngs::SmallRng;
+	use rand::SeedableRng;
+
+	#[test]
+	fn should_keep_snapshots_unchanged_and_reuse_released_buffers() {
+		let mut world = World::random(
+			Vector::new(1000.0, 1000.0),
+			10,
+			|| DefaultBehavior,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		);
+		world.simulate_step();
+
+		let snapshot = world.snapshot();
+		assert!(Arc::ptr_eq(&snapshot.agents, &world.agents));
+		let positions = |agents: &[Agent]| agents.iter().map(|agent| agent.position).collect::<Vec<_>>();
+		let snapshot_positions = positions(&snapshot.agents);
+		world.simulate_step();
+		world.simulate_step();
+		// the snapshot isn't touched by later steps
+		assert_eq!(snapshot_positions, positions(&snapshot.agents));
+		assert_ne!(snapshot_positions, positions(world.agents()));
+		assert!(!Arc::ptr_eq(&snapshot.agents, &world.agents));
+
+		let buffer = world.spare_agents.as_ptr();
+		world.simulate_step();
+		world.simulate_step();
+		assert_eq!(buffer, world.spare_agents.as_ptr());
+	}
+
+	#[test]
+	fn should_find_the_same_visible_and_reachable_agents_in_a_sharded_world() {
+		let world = World::random(
+			Vector::new(200.0, 200.0),
+			1000,
+			|| DefaultBehavior,
+			true,
+			&mut SmallRng::seed_from_u64(42),
+		)
+		.with_physics(Physics {
+			view_distance: 30.0,
+			..Physics::default()
+		})
+		.with_shards(9);
+		let shard_grid = world.shard_grid.expect("World is sharded");
+		let regions = shard_grid.partition(&world.agents, world.physics.view_distance);
+
+		let visible_ids = |world_view: &WorldView| world_view.visible().map(|(id, _)| id).collect::<Vec<_>>();
+		let reachable_ids = |world_view: &WorldView| world_view.reachable().map(|(id, _)| id).collect::<Vec<_>>();
+		for region in &regions {
+			for &id in &region.members {
+				let unsharded = world.world_view(id, world.agents[id].clone());
+				let sharded = world.world_view_in(id, world.agents[id].clone(), &region.neighborhood);
+				assert_eq!(visible_ids(&unsharded), visible_ids(&sharded));
+				assert_eq!(reachable_ids(&unsharded), reachable_ids(&sharded));
+				// the neighborhood is much smaller than the world
+				assert!(region.neighborhood.len() < world.agents.len() / 2);
+			}
+		}
+	}
+
+	/// Deterministic, unlike the shipped behaviors: Goes after the nearest visible agent and tags it once in reach
+	struct NearestBehavior;
+
+	impl Behavior for NearestBehavior {
+		fn perform_step(&mut self, world_view: &mut WorldView) -> Operation {
+			let nearest = world_view.nearest_visible(|_, _| true);
+			let tag = nearest
+				.as_ref()
+				.filter(|(_, relationship)| world_view.physics().is_reachable(relationship))
+				.map(|&(id, _)| id);
+			Operation {
+				direction: world_view.our_agent().heading
+					+ nearest.map_or(0.5, |(_, relationship)| relationship.direction),
+				velocity: f32::MAX,
+				tag,
+			}
+		}
+	}
+
+	#[test]
+	fn should_simulate_the_same_with_and_without_shards() {
+		let physics = Physics {
+			view_distance: 30.0,
+			..Physics::default()
+		};
+		let world = || {
+			World::random(
+				Vector::new(200.0, 200.0),
+				500,
+				|| NearestBehavior,
+				false,
+				&mut SmallRng::seed_from_u64(42),
+			)
+			.with_physics(physics)
+		};
+		let mut unsharded = world();
+		let mut sharded = world().with_shards(9);
+
+		let positions = |world: &World| world.agents().iter().map(|agent| agent.position).collect::<Vec<_>>();
+		for _ in 0..20 {
+			unsharded.simulate_step();
+			sharded.simulate_step();
+			assert_eq!(positions(&unsharded), positions(&sharded));
+			assert_eq!(unsharded.it(), sharded.it());
+		}
+	}
+
+	#[test]
+	fn should_prefer_the_tag_of_it_and_then_of_the_lowest_id() {
+		let world = World::random(
+			Vector::new(100.0, 100.0),
+			10,
+			|| DefaultBehavior,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		);
+		let it = world.it();
+		let other = Id::from((usize::from(it) + 1) % 10);
+		let another = Id::from((usize::from(it) + 2) % 10);
+
+		assert!(world.tag_takes_precedence(it, other));
+		assert!(!world.tag_takes_precedence(other, it));
+		assert_eq!(other < another, world.tag_takes_precedence(other, another));
+	}
+
+	#[test]
+	fn should_answer_targeted_queries_like_the_collected_agents() {
+		let agent_count = 200;
+		for layout in [AgentLayout::ArrayOfStructs, AgentLayout::StructOfArrays] {
+			let world = World::random(
+				Vector::new(100.0, 100.0),
+				agent_count,
+				|| DefaultBehavior,
+				false,
+				&mut SmallRng::seed_from_u64(42),
+			)
+			.with_layout(layout);
+			let mut world_view = world.world_view(Id::from(0), world.agents[0].clone());
+
+			let visible_agents = world_view.visible_agents().clone();
+			let mut by_distance = visible_agents
+				.iter()
+				.map(|(&id, relationship)| (relationship.distance, id))
+				.collect::<Vec<_>>();
+			by_distance.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+			assert!(by_distance.len() > 5);
+
+			for id in (0..agent_count).map(Id::from) {
+				let expected = visible_agents.get(&id).map(|relationship| relationship.distance);
+				assert_eq!(
+					expected,
+					world_view.relation_to(id).map(|relationship| relationship.distance)
+				);
+			}
+			assert_eq!(
+				Some(by_distance[1].1),
+				world_view
+					.nearest_visible(|id, _| id != by_distance[0].1)
+					.map(|(id, _)| id)
+			);
+			assert_eq!(
+				by_distance[..5].iter().map(|&(_, id)| id).collect::<Vec<_>>(),
+				world_view
+					.k_nearest(5)
+					.into_iter()
+					.map(|(id, _)| id)
+					.collect::<Vec<_>>()
+			);
+			assert!(world_view.k_nearest(0).is_empty());
+			assert_eq!(by_distance.len(), world_view.k_nearest(agent_count).len());
+			let radius = by_distance[2].0;
+			assert_eq!(3, world_view.within_radius(radius).count());
+			assert_eq!(world_view.reachable_agents().len(), world_view.reachable().count());
+		}
+	}
+
+	#[test]
+	fn should_count_tags_and_iterations_as_it() {
+		let mut world = World::random(
+			Vector::new(100.0, 100.0),
+			2,
+			|| NearestBehavior,
+			false,
+			&mut SmallRng::seed_from_u64(42),
+		);
+		// "it" is on top of the other agent, so it tags it in the first step
+		world.reset(vec![world.agents[0].clone(); 2], Id::from(0));
+
+		world.simulate_step();
+
+		let statistics = world.statistics();
+		assert_eq!(Id::from(1), world.it());
+		assert_eq!(1, statistics.tag_count);
+		assert_eq!(Some(1), statistics.last_tag_iteration);
+		assert_eq!(1, statistics[Id::from(0)].tags);
+		assert_eq!(0, statistics[Id::from(0)].times_tagged);
+		assert_eq!(1, statistics[Id::from(0)].iterations_as_it);
+		assert_eq!(0, statistics[Id::from(1)].tags);
+		assert_eq!(1, statistics[Id::from(1)].times_tagged);
+		assert_eq!(0, statistics[Id::from(1)].iterations_as_it);
 	}
 }